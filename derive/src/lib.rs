@@ -0,0 +1,343 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Ident, Result};
+
+enum Direction {
+    Serverbound,
+    Clientbound,
+}
+
+struct PacketAttribute {
+    id: Expr,
+    // only server-bound packets are dispatched by state
+    state: Option<Ident>,
+    direction: Direction,
+    min_protocol: Option<Expr>,
+    // the largest packet length (id included) accepted for a server-bound packet
+    max_length: Option<Expr>,
+    // read_from_stream / write_to_stream are implemented by hand
+    manual: bool,
+}
+
+fn parse_packet_attribute(input: &DeriveInput) -> Result<PacketAttribute> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|a| a.path().is_ident("packet"))
+        .ok_or_else(|| Error::new(Span::call_site(), "missing #[packet(...)] attribute"))?;
+
+    let mut id = None;
+    let mut state = None;
+    let mut direction = None;
+    let mut min_protocol = None;
+    let mut max_length = None;
+    let mut manual = false;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("id") {
+            id = Some(meta.value()?.parse::<Expr>()?);
+        } else if meta.path.is_ident("state") {
+            let value: Ident = meta.value()?.parse()?;
+            state = Some(match value.to_string().as_str() {
+                "Handshaking" => Ident::new("HANDSHAKING", value.span()),
+                "Status" => Ident::new("STATUS", value.span()),
+                "Login" => Ident::new("LOGIN", value.span()),
+                _ => return Err(meta.error("state must be Handshaking, Status or Login")),
+            });
+        } else if meta.path.is_ident("direction") {
+            let value: Ident = meta.value()?.parse()?;
+            direction = Some(match value.to_string().as_str() {
+                "Serverbound" => Direction::Serverbound,
+                "Clientbound" => Direction::Clientbound,
+                _ => return Err(meta.error("direction must be Serverbound or Clientbound")),
+            });
+        } else if meta.path.is_ident("min_protocol") {
+            min_protocol = Some(meta.value()?.parse::<Expr>()?);
+        } else if meta.path.is_ident("max_length") {
+            max_length = Some(meta.value()?.parse::<Expr>()?);
+        } else if meta.path.is_ident("manual") {
+            manual = true;
+        } else {
+            return Err(meta.error("unknown packet attribute"));
+        }
+        Ok(())
+    })?;
+
+    let missing = |name: &str| Error::new_spanned(attr, format!("missing `{}`", name));
+    let direction = direction.ok_or_else(|| missing("direction"))?;
+    if let Direction::Clientbound = direction {
+        if let Some(v) = &max_length {
+            return Err(Error::new_spanned(
+                v,
+                "max_length is only supported by serverbound packets",
+            ));
+        }
+        if let Some(v) = &state {
+            return Err(Error::new_spanned(
+                v,
+                "state is only supported by serverbound packets",
+            ));
+        }
+    } else if state.is_none() {
+        return Err(missing("state"));
+    }
+    Ok(PacketAttribute {
+        id: id.ok_or_else(|| missing("id"))?,
+        state,
+        direction,
+        min_protocol,
+        max_length,
+        manual,
+    })
+}
+
+// fields marked with #[packet(skip)] are not on the wire and are filled with Default
+fn is_skipped(field: &syn::Field) -> Result<bool> {
+    let mut skip = false;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("packet")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown packet field attribute"))
+            }
+        })?;
+    }
+    Ok(skip)
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let attribute = parse_packet_attribute(&input)?;
+    let name = &input.ident;
+    let id = &attribute.id;
+    let state = &attribute.state;
+    let min_protocol = match &attribute.min_protocol {
+        Some(v) => quote!(#v),
+        None => quote!(0),
+    };
+    let max_length = match &attribute.max_length {
+        Some(v) => quote!(#v),
+        None => quote!(crate::packet::MAX_PACKET_LENGTH),
+    };
+
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => f.named.iter().collect::<Vec<_>>(),
+            Fields::Unit => vec![],
+            Fields::Unnamed(_) => {
+                return Err(Error::new_spanned(name, "tuple structs are not supported"))
+            }
+        },
+        _ => return Err(Error::new_spanned(name, "only structs can be packets")),
+    };
+
+    let mut wire_fields = vec![];
+    let mut skipped_fields = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        if is_skipped(field)? {
+            skipped_fields.push(ident);
+        } else {
+            wire_fields.push((ident, &field.ty));
+        }
+    }
+
+    let packet_impl = quote! {
+        impl crate::packet::Packet for #name {
+            const ID: i32 = #id;
+            const MIN_PROTOCOL: i32 = #min_protocol;
+        }
+    };
+
+    let codec_impl = match (&attribute.direction, attribute.manual) {
+        (_, true) => quote!(),
+        (Direction::Serverbound, false) => {
+            let reads = wire_fields.iter().map(|(ident, ty)| {
+                quote!(#ident: <#ty as crate::packet::datatype::Decode>::decode(stream)?)
+            });
+            quote! {
+                impl crate::packet::ReadPacket for #name {
+                    fn read_from_stream(
+                        _: &mut crate::session::Session,
+                        stream: &mut impl ::std::io::Read,
+                    ) -> crate::packet::Result<Box<dyn crate::packet::ServerBoundPacketBody>> {
+                        Ok(Box::new(#name {
+                            #(#reads,)*
+                            #(#skipped_fields: Default::default(),)*
+                        }))
+                    }
+                }
+            }
+        }
+        (Direction::Clientbound, false) => {
+            let writes = wire_fields.iter().map(|(ident, _)| quote!(&self.#ident));
+            quote! {
+                impl crate::packet::ClientBoundPacketBody for #name {
+                    fn write_to_stream(
+                        &self,
+                        session: &mut crate::session::Session,
+                        stream: &mut dyn ::std::io::Write,
+                    ) -> crate::packet::Result<()> {
+                        crate::packet::write_packet::<Self>(session, stream, &[#(#writes),*])
+                    }
+                }
+            }
+        }
+    };
+
+    let registration = match (&attribute.direction, state) {
+        (Direction::Serverbound, Some(state)) => quote! {
+            ::inventory::submit! {
+                crate::packet::registry::ServerBoundRegistration {
+                    name: stringify!(#name),
+                    state: crate::session::SessionState::#state,
+                    id: #id,
+                    min_protocol: #min_protocol,
+                    max_length: #max_length,
+                    read: |session, mut stream| {
+                        <#name as crate::packet::ReadPacket>::read_from_stream(session, &mut stream)
+                    },
+                }
+            }
+        },
+        _ => quote!(),
+    };
+
+    Ok(quote! {
+        #packet_impl
+        #codec_impl
+        #registration
+    })
+}
+
+#[proc_macro_derive(Packet, attributes(packet))]
+pub fn derive_packet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn expand_to_string(input: DeriveInput) -> String {
+        expand(input).unwrap().to_string()
+    }
+
+    fn expand_error(input: DeriveInput) -> String {
+        expand(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_expand_serverbound() {
+        let expanded = expand_to_string(parse_quote! {
+            #[packet(id = 0x01, state = Status, direction = Serverbound, max_length = 1 + 8)]
+            struct Ping {
+                payload: long::Long,
+                #[packet(skip)]
+                received: bool,
+            }
+        });
+        assert!(expanded.contains("const ID : i32 = 0x01"));
+        assert!(expanded.contains("const MIN_PROTOCOL : i32 = 0"));
+        assert!(expanded.contains(
+            "payload : < long :: Long as crate :: packet :: datatype :: Decode > :: decode (stream) ?"
+        ));
+        assert!(expanded.contains("received : Default :: default ()"));
+        assert!(expanded.contains("state : crate :: session :: SessionState :: STATUS"));
+        assert!(expanded.contains("max_length : 1 + 8"));
+    }
+
+    #[test]
+    fn test_expand_clientbound() {
+        let expanded = expand_to_string(parse_quote! {
+            #[packet(id = 0x04, direction = Clientbound, min_protocol = 393)]
+            struct Request {
+                message_id: varint::VarInt,
+                channel: string::String,
+            }
+        });
+        assert!(expanded.contains("const MIN_PROTOCOL : i32 = 393"));
+        assert!(expanded.contains(
+            "crate :: packet :: write_packet :: < Self > (session , stream , & [& self . message_id , & self . channel])"
+        ));
+        // only server-bound packets are registered for dispatch
+        assert!(!expanded.contains("inventory"));
+    }
+
+    #[test]
+    fn test_expand_manual() {
+        let expanded = expand_to_string(parse_quote! {
+            #[packet(id = 0x00, state = Login, direction = Serverbound, manual)]
+            struct LoginStart {
+                name: string::String,
+            }
+        });
+        assert!(!expanded.contains("impl crate :: packet :: ReadPacket"));
+        assert!(expanded
+            .contains("< LoginStart as crate :: packet :: ReadPacket > :: read_from_stream"));
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert_eq!(
+            expand_error(parse_quote! {
+                #[packet(id = 0x00, state = Status, direction = Clientbound)]
+                struct Response {}
+            }),
+            "state is only supported by serverbound packets"
+        );
+        assert_eq!(
+            expand_error(parse_quote! {
+                #[packet(id = 0x00, direction = Serverbound)]
+                struct Request {}
+            }),
+            "missing `state`"
+        );
+        assert_eq!(
+            expand_error(parse_quote! {
+                #[packet(id = 0x00, direction = Clientbound, max_length = 1)]
+                struct Response {}
+            }),
+            "max_length is only supported by serverbound packets"
+        );
+        assert_eq!(
+            expand_error(parse_quote! {
+                #[packet(id = 0x00, state = Play, direction = Serverbound)]
+                struct Request {}
+            }),
+            "state must be Handshaking, Status or Login"
+        );
+        assert_eq!(
+            expand_error(parse_quote! {
+                #[packet(id = 0x00, direction = Clientbound, compressed)]
+                struct Response {}
+            }),
+            "unknown packet attribute"
+        );
+        assert_eq!(
+            expand_error(parse_quote! {
+                struct Response {}
+            }),
+            "missing #[packet(...)] attribute"
+        );
+        assert_eq!(
+            expand_error(parse_quote! {
+                #[packet(id = 0x00, direction = Clientbound)]
+                struct Response(string::String);
+            }),
+            "tuple structs are not supported"
+        );
+        assert_eq!(
+            expand_error(parse_quote! {
+                #[packet(id = 0x00, direction = Clientbound)]
+                enum Response {}
+            }),
+            "only structs can be packets"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+use fake_minecraft_server_derive::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0x00, state = Status)]
+struct StatusRequest {}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use fake_minecraft_server_derive::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0x01, state = Status, direction = Serverbound)]
+struct PingRequest {
+    #[packet(optional)]
+    payload: i64,
+}
+
+fn main() {}
@@ -0,0 +1,542 @@
+use crate::cidr::Cidr;
+use config::Config;
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_BANNED_IPS_FILE: &str = "banned-ips.json";
+const DEFAULT_BANNED_PLAYERS_FILE: &str = "banned-players.json";
+const DEFAULT_WHITELIST_FILE: &str = "whitelist.json";
+const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IpBanEntry {
+    pub ip: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PlayerBanEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WhitelistEntry {
+    pub uuid: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct BanList {
+    // invalid entries are ignored, but kept so that they are written back as they were
+    ips: Vec<(Option<Cidr>, IpBanEntry)>,
+    players: Vec<PlayerBanEntry>,
+    whitelist: Option<Vec<WhitelistEntry>>,
+    // new bans are written back to these files, unless they could not be loaded
+    ips_file: Option<String>,
+    players_file: Option<String>,
+}
+
+impl BanList {
+    pub fn new(
+        ips: Vec<IpBanEntry>,
+        players: Vec<PlayerBanEntry>,
+        whitelist: Option<Vec<WhitelistEntry>>,
+    ) -> BanList {
+        let ips = ips
+            .into_iter()
+            .map(|entry| match Cidr::from_str(&entry.ip) {
+                Ok(cidr) => (Some(cidr), entry),
+                Err(e) => {
+                    warn!("Ignoring IP ban: {}", e);
+                    (None, entry)
+                }
+            })
+            .collect();
+
+        BanList {
+            ips,
+            players,
+            whitelist,
+            ips_file: None,
+            players_file: None,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> BanList {
+        let ips_file = config
+            .get::<String>("banned_ips_file")
+            .unwrap_or(DEFAULT_BANNED_IPS_FILE.into());
+        let players_file = config
+            .get::<String>("banned_players_file")
+            .unwrap_or(DEFAULT_BANNED_PLAYERS_FILE.into());
+        let ips: Option<Vec<IpBanEntry>> = load_json(&ips_file);
+        let players: Option<Vec<PlayerBanEntry>> = load_json(&players_file);
+        let whitelist: Option<Vec<WhitelistEntry>> =
+            if config.get::<bool>("whitelist").unwrap_or(false) {
+                Some(
+                    load_json(
+                        &config
+                            .get::<String>("whitelist_file")
+                            .unwrap_or(DEFAULT_WHITELIST_FILE.into()),
+                    )
+                    .unwrap_or_default(),
+                )
+            } else {
+                None
+            };
+
+        let mut ban_list = BanList::new(
+            ips.clone().unwrap_or_default(),
+            players.clone().unwrap_or_default(),
+            whitelist,
+        );
+        ban_list.ips_file = writable(ips_file, &ips);
+        ban_list.players_file = writable(players_file, &players);
+        info!(
+            "Loaded {} IP bans, {} player bans{}",
+            ban_list
+                .ips
+                .iter()
+                .filter(|(cidr, _)| cidr.is_some())
+                .count(),
+            ban_list.players.len(),
+            match &ban_list.whitelist {
+                Some(w) => format!(", {} whitelisted players", w.len()),
+                None => "".to_string(),
+            }
+        );
+        ban_list
+    }
+
+    // an address or a CIDR range, banned forever
+    pub fn ban_ip(&mut self, ip: &str, source: &str) -> Result<(), String> {
+        let cidr = Cidr::from_str(ip).map_err(|e| e.to_string())?;
+        let entry = IpBanEntry {
+            ip: ip.to_string(),
+            created: Some(format_date(now())),
+            source: Some(source.to_string()),
+            expires: Some("forever".to_string()),
+            reason: Some(DEFAULT_BAN_REASON.to_string()),
+        };
+        self.ips.push((Some(cidr), entry));
+
+        if let Some(path) = &self.ips_file {
+            let entries: Vec<&IpBanEntry> = self.ips.iter().map(|(_, entry)| entry).collect();
+            save_json(path, &entries);
+        }
+        Ok(())
+    }
+
+    // the UUID is not looked up, so the ban matches the name only
+    pub fn ban_player(&mut self, name: &str, source: &str) {
+        self.players.push(PlayerBanEntry {
+            uuid: None,
+            name: Some(name.to_string()),
+            created: Some(format_date(now())),
+            source: Some(source.to_string()),
+            expires: Some("forever".to_string()),
+            reason: Some(DEFAULT_BAN_REASON.to_string()),
+        });
+
+        if let Some(path) = &self.players_file {
+            save_json(path, &self.players);
+        }
+    }
+
+    // the address must be the real one, after any forwarding by a proxy
+    pub fn check(&self, address: &IpAddr, username: &str, uuid: Option<u128>) -> Option<String> {
+        self.check_ip(address)
+            .or_else(|| self.check_player(username, uuid))
+    }
+
+    // returns the disconnect message when the address is banned
+    pub fn check_ip(&self, address: &IpAddr) -> Option<String> {
+        let now = now();
+        self.ips
+            .iter()
+            .find(|(cidr, entry)| {
+                cidr.as_ref().is_some_and(|c| c.contains(address))
+                    && !is_expired(&entry.expires, now)
+            })
+            .map(|(_, entry)| {
+                ban_message(
+                    "Your IP address is banned from this server.",
+                    &entry.reason,
+                    &entry.expires,
+                )
+            })
+    }
+
+    // returns the disconnect message when the player is banned or not whitelisted
+    pub fn check_player(&self, username: &str, uuid: Option<u128>) -> Option<String> {
+        let now = now();
+        let banned = self.players.iter().find(|entry| {
+            matches_player(&entry.uuid, &entry.name, username, uuid)
+                && !is_expired(&entry.expires, now)
+        });
+        if let Some(entry) = banned {
+            return Some(ban_message(
+                "You are banned from this server.",
+                &entry.reason,
+                &entry.expires,
+            ));
+        }
+
+        if let Some(whitelist) = &self.whitelist {
+            if !whitelist
+                .iter()
+                .any(|entry| matches_player(&entry.uuid, &entry.name, username, uuid))
+            {
+                return Some("You are not white-listed on this server!".to_string());
+            }
+        }
+
+        None
+    }
+}
+
+// None when the file exists but could not be loaded
+fn load_json<T: DeserializeOwned>(path: &str) -> Option<Vec<T>> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Some(vec![]),
+        Err(e) => {
+            warn!("Could not read {}: {}", path, e);
+            return None;
+        }
+    };
+    match serde_json::from_str::<Vec<T>>(&content) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            warn!("Could not parse {}: {}", path, e);
+            None
+        }
+    }
+}
+
+// a file that could not be loaded is never overwritten, so that no ban is lost
+fn writable<T>(path: String, loaded: &Option<Vec<T>>) -> Option<String> {
+    if loaded.is_none() {
+        warn!("New bans will not be written to {}", path);
+        return None;
+    }
+    Some(path)
+}
+
+// replaced at once, so that the file is never left half written
+fn save_json<T: Serialize>(path: &str, entries: &T) {
+    let json = serde_json::to_string_pretty(entries).unwrap_or_default();
+    let temporary_path = format!("{}.tmp", path);
+    if let Err(e) = fs::write(&temporary_path, json).and_then(|_| fs::rename(&temporary_path, path))
+    {
+        warn!("Could not write {}: {}", path, e);
+    }
+}
+
+// a UUID match wins, otherwise the name is compared case-insensitively
+fn matches_player(
+    entry_uuid: &Option<String>,
+    entry_name: &Option<String>,
+    username: &str,
+    uuid: Option<u128>,
+) -> bool {
+    let entry_uuid = entry_uuid
+        .as_ref()
+        .and_then(|u| u128::from_str_radix(&u.replace('-', ""), 16).ok());
+    if entry_uuid.is_some() && entry_uuid == uuid {
+        return true;
+    }
+    match entry_name {
+        Some(name) => name.eq_ignore_ascii_case(username),
+        None => false,
+    }
+}
+
+fn ban_message(header: &str, reason: &Option<String>, expires: &Option<String>) -> String {
+    let mut message = format!(
+        "{}\nReason: {}",
+        header,
+        reason.as_deref().unwrap_or(DEFAULT_BAN_REASON)
+    );
+    if let Some(expires) = expires {
+        if parse_date(expires).is_some() {
+            message.push_str(&format!("\nYour ban will be removed on {}", expires));
+        }
+    }
+    message
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// "forever" (and anything unparsable) never expires
+fn is_expired(expires: &Option<String>, now: i64) -> bool {
+    match expires.as_deref().and_then(parse_date) {
+        Some(expires) => expires <= now,
+        None => false,
+    }
+}
+
+// vanilla date format: "2024-01-15 12:34:56 +0900"
+fn parse_date(s: &str) -> Option<i64> {
+    let fields: Vec<&str> = s.split(' ').collect();
+    let [date, time, offset] = fields[..] else {
+        return None;
+    };
+
+    let date: Vec<i64> = date.split('-').filter_map(|f| f.parse().ok()).collect();
+    let time: Vec<i64> = time.split(':').filter_map(|f| f.parse().ok()).collect();
+    let (&[year, month, day], &[hour, minute, second]) = (&date[..], &time[..]) else {
+        return None;
+    };
+    if offset.len() != 5 || !offset.is_char_boundary(1) || !offset.is_char_boundary(3) {
+        return None;
+    }
+    let offset_sign = match &offset[..1] {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let offset_hours: i64 = offset[1..3].parse().ok()?;
+    let offset_minutes: i64 = offset[3..].parse().ok()?;
+
+    // days from civil (http://howardhinnant.github.io/date_algorithms.html)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    Some(
+        days * 86400 + hour * 3600 + minute * 60 + second
+            - offset_sign * (offset_hours * 3600 + offset_minutes * 60),
+    )
+}
+
+// the inverse of parse_date, always in UTC
+fn format_date(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86400);
+    let seconds = timestamp.rem_euclid(86400);
+
+    // civil from days (http://howardhinnant.github.io/date_algorithms.html)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} +0000",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ban_list() -> BanList {
+        let ips: Vec<IpBanEntry> = serde_json::from_str(
+            r#"[
+                {"ip": "203.0.113.7", "created": "2023-10-01 00:00:00 +0000", "source": "Server", "expires": "forever", "reason": "Spam"},
+                {"ip": "198.51.100.0/24", "expires": "forever"},
+                {"ip": "192.0.2.1", "expires": "2000-01-01 00:00:00 +0000", "reason": "Expired"}
+            ]"#,
+        )
+        .unwrap();
+        let players: Vec<PlayerBanEntry> = serde_json::from_str(
+            r#"[
+                {"uuid": "069a79f4-44e9-4726-a5be-fca90e38aaf5", "name": "Notch", "created": "2023-10-01 00:00:00 +0000", "source": "Server", "expires": "forever", "reason": "Griefing"},
+                {"name": "jeb_", "expires": "2999-01-01 00:00:00 +0000"}
+            ]"#,
+        )
+        .unwrap();
+        let whitelist: Vec<WhitelistEntry> = serde_json::from_str(
+            r#"[{"uuid": "853c80ef-3c37-49fd-aa49-938b674adae6", "name": "jeb_"}, {"name": "Dinnerbone"}]"#,
+        )
+        .unwrap();
+
+        BanList::new(ips, players, Some(whitelist))
+    }
+
+    #[test]
+    fn test_check_ip() {
+        let ban_list = ban_list();
+        assert_eq!(
+            ban_list.check_ip(&IpAddr::from_str("203.0.113.7").unwrap()),
+            Some("Your IP address is banned from this server.\nReason: Spam".to_string())
+        );
+        assert_eq!(
+            ban_list.check_ip(&IpAddr::from_str("198.51.100.42").unwrap()),
+            Some(
+                "Your IP address is banned from this server.\nReason: Banned by an operator."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            ban_list.check_ip(&IpAddr::from_str("192.0.2.1").unwrap()),
+            None
+        );
+        assert_eq!(
+            ban_list.check_ip(&IpAddr::from_str("203.0.113.8").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_player() {
+        let ban_list = ban_list();
+        assert_eq!(
+            ban_list.check_player("renamed", Some(0x069a79f444e94726a5befca90e38aaf5)),
+            Some("You are banned from this server.\nReason: Griefing".to_string())
+        );
+        assert_eq!(
+            ban_list.check_player("JEB_", None),
+            Some(
+                "You are banned from this server.\nReason: Banned by an operator.\nYour ban will be removed on 2999-01-01 00:00:00 +0000"
+                    .to_string()
+            )
+        );
+        assert_eq!(ban_list.check_player("dinnerbone", None), None);
+        assert_eq!(
+            ban_list.check_player("Steve", None),
+            Some("You are not white-listed on this server!".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let ban_list = ban_list();
+        let banned = IpAddr::from_str("203.0.113.7").unwrap();
+        let allowed = IpAddr::from_str("203.0.113.8").unwrap();
+        assert_eq!(
+            ban_list.check(&banned, "Dinnerbone", None),
+            Some("Your IP address is banned from this server.\nReason: Spam".to_string())
+        );
+        assert_eq!(
+            ban_list.check(&allowed, "Notch", None),
+            Some("You are banned from this server.\nReason: Griefing".to_string())
+        );
+        assert_eq!(ban_list.check(&allowed, "Dinnerbone", None), None);
+    }
+
+    #[test]
+    fn test_save() {
+        let directory = std::env::temp_dir().join("fake-minecraft-server-ban-list");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let ips_file = directory.join("banned-ips.json");
+        let players_file = directory.join("banned-players.json");
+        fs::write(
+            &ips_file,
+            r#"[{"ip": "203.0.113.0/33", "reason": "Typo"}, {"ip": "192.0.2.1"}]"#,
+        )
+        .unwrap();
+        fs::write(&players_file, "[{").unwrap();
+        let config = Config::builder()
+            .set_override("banned_ips_file", ips_file.to_str())
+            .unwrap()
+            .set_override("banned_players_file", players_file.to_str())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut ban_list = BanList::from_config(&config);
+        ban_list.ban_ip("198.51.100.7", "Rcon").unwrap();
+        ban_list.ban_player("Notch", "Rcon");
+
+        assert!(ban_list
+            .check_ip(&IpAddr::from_str("203.0.113.1").unwrap())
+            .is_none());
+        // the invalid range is still there, in its place
+        let ips: Vec<IpBanEntry> =
+            serde_json::from_str(&fs::read_to_string(&ips_file).unwrap()).unwrap();
+        let ips: Vec<&str> = ips.iter().map(|entry| entry.ip.as_str()).collect();
+        assert_eq!(ips, ["203.0.113.0/33", "192.0.2.1", "198.51.100.7"]);
+        // the broken file is left alone, while the ban still applies until a restart
+        assert_eq!(fs::read_to_string(&players_file).unwrap(), "[{");
+        assert!(ban_list.check_player("Notch", None).is_some());
+        assert!(!directory.join("banned-ips.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01 00:00:00 +0000"), Some(0));
+        assert_eq!(parse_date("2023-10-19 15:00:00 +0900"), Some(1697695200));
+        assert_eq!(
+            parse_date("2000-03-01 00:00:00 -0130"),
+            Some(951868800 + 5400)
+        );
+        assert_eq!(parse_date("forever"), None);
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01 00:00:00 +0000");
+        assert_eq!(format_date(1697695200), "2023-10-19 06:00:00 +0000");
+        assert_eq!(format_date(951868800 + 5400), "2000-03-01 01:30:00 +0000");
+        assert_eq!(parse_date(&format_date(1709251199)), Some(1709251199));
+    }
+
+    #[test]
+    fn test_ban() {
+        let mut ban_list = BanList::default();
+        let address = IpAddr::from_str("198.51.100.42").unwrap();
+        assert_eq!(ban_list.check_ip(&address), None);
+        ban_list.ban_ip("198.51.100.0/24", "Rcon").unwrap();
+        assert!(ban_list.check_ip(&address).is_some());
+        assert!(ban_list.ban_ip("Notch", "Rcon").is_err());
+
+        assert_eq!(ban_list.check_player("Notch", None), None);
+        ban_list.ban_player("Notch", "Rcon");
+        assert_eq!(
+            ban_list.check_player("notch", None),
+            Some("You are banned from this server.\nReason: Banned by an operator.".to_string())
+        );
+
+        let json = serde_json::to_value(&ban_list.players[0]).unwrap();
+        assert_eq!(json["name"], "Notch");
+        assert_eq!(json["source"], "Rcon");
+        assert_eq!(json["expires"], "forever");
+        assert!(json.get("uuid").is_none());
+    }
+}
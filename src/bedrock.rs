@@ -0,0 +1,229 @@
+use crate::server::Server;
+use crate::session::SessionState;
+use crate::virtual_host::Profile;
+use log::{debug, info, warn};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::time::Instant;
+
+// https://wiki.vg/Raknet_Protocol#Unconnected_Ping
+const UNCONNECTED_PING: u8 = 0x01;
+const UNCONNECTED_PING_OPEN_CONNECTIONS: u8 = 0x02;
+const UNCONNECTED_PONG: u8 = 0x1c;
+const MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+// id, time, magic and client GUID
+const PING_LENGTH: usize = 1 + 8 + 16 + 8;
+pub const DEFAULT_PORT: u16 = 19132;
+// 1.20.50
+pub const DEFAULT_PROTOCOL_VERSION: i32 = 630;
+
+// answers the RakNet unconnected ping of the Bedrock Edition with the status of the default profile
+#[derive(Debug)]
+pub struct BedrockServer {
+    server: Arc<Server>,
+    port: u16,
+    protocol_version: i32,
+    guid: i64,
+}
+
+impl BedrockServer {
+    pub fn new(server: Arc<Server>, port: u16, protocol_version: i32) -> BedrockServer {
+        let mut bytes: [u8; 8] = [0; 8];
+        if openssl::rand::rand_bytes(&mut bytes).is_err() {
+            warn!("Could not generate a server GUID");
+        }
+        BedrockServer {
+            server,
+            port,
+            protocol_version,
+            guid: i64::from_be_bytes(bytes) & i64::MAX,
+        }
+    }
+
+    pub fn run(&self, socket: &UdpSocket) -> io::Result<()> {
+        let mut buf = [0; 1500];
+        loop {
+            let (n, source) = socket.recv_from(&mut buf)?;
+            if let Some(response) = self.handle(&buf[..n], source) {
+                if let Err(e) = socket.send_to(&response, source) {
+                    warn!("[Bedrock] Could not respond to {}: {}", source, e);
+                }
+            }
+        }
+    }
+
+    // None for anything but an unconnected ping, which needs a RakNet connection
+    pub fn handle(&self, request: &[u8], source: SocketAddr) -> Option<Vec<u8>> {
+        if request.len() < PING_LENGTH
+            || ![UNCONNECTED_PING, UNCONNECTED_PING_OPEN_CONNECTIONS].contains(&request[0])
+            || request[9..25] != MAGIC
+        {
+            debug!("[Bedrock] Ignoring a packet from {}", source);
+            return None;
+        }
+        let time = &request[1..9];
+        let client_guid = i64::from_be_bytes(request[25..33].try_into().ok()?);
+        // limited like a status request, and dropped without a pong
+        let mut rate_limiter = self.server.rate_limiter.lock().unwrap();
+        if let Err(throttle) =
+            rate_limiter.check(&SessionState::STATUS, source.ip(), Instant::now())
+        {
+            debug!(
+                "[Bedrock] Throttled {} ({}); {}",
+                source, throttle, rate_limiter
+            );
+            return None;
+        }
+        drop(rate_limiter);
+        self.log_session(source, client_guid);
+
+        let server_id = self.server_id(&self.server.virtual_hosts.read().unwrap().default);
+        let mut response = vec![UNCONNECTED_PONG];
+        response.extend_from_slice(time);
+        response.extend_from_slice(&self.guid.to_be_bytes());
+        response.extend_from_slice(&MAGIC);
+        response.extend_from_slice(&(server_id.len() as u16).to_be_bytes());
+        response.extend_from_slice(server_id.as_bytes());
+        Some(response)
+    }
+
+    // a ping is the whole session, located like a Java Edition connection but counted apart
+    fn log_session(&self, source: SocketAddr, client_guid: i64) {
+        let location = if self.server.geoip.is_enabled() {
+            self.server.geoip.lookup(&source.ip())
+        } else {
+            Default::default()
+        };
+        info!(
+            "[Bedrock] {} Unconnected Ping (client GUID: {}, {})",
+            source, client_guid, location
+        );
+        self.server.record_bedrock_ping();
+    }
+
+    // MCPE;<motd>;<protocol>;<version>;<online>;<max>;<guid>;<sub motd>;<game mode>;<game mode id>;<port>;<port v6>;
+    fn server_id(&self, profile: &Profile) -> String {
+        // the fields are separated by semicolons, and each MOTD line is shown on its own
+        let description = profile.description.replace(';', " ");
+        let mut lines = description.lines();
+        let fields = [
+            "MCPE".to_string(),
+            lines.next().unwrap_or_default().to_string(),
+            self.protocol_version.to_string(),
+            profile.version_name.replace(';', " "),
+            profile.players_online.to_string(),
+            profile.players_max.to_string(),
+            self.guid.to_string(),
+            lines.next().unwrap_or_default().to_string(),
+            "Survival".to_string(),
+            "1".to_string(),
+            self.port.to_string(),
+            self.port.to_string(),
+        ];
+        format!("{};", fields.join(";"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rate_limit::{BucketConfig, RateLimiter};
+    use std::sync::Mutex;
+
+    const SOURCE: &str = "203.0.113.7:54321";
+
+    fn ping(id: u8) -> Vec<u8> {
+        let mut request = vec![id];
+        request.extend_from_slice(&0x0102030405060708_i64.to_be_bytes());
+        request.extend_from_slice(&MAGIC);
+        request.extend_from_slice(&42_i64.to_be_bytes());
+        request
+    }
+
+    #[test]
+    fn test_unconnected_pong() {
+        let server = Arc::new(Server::default());
+        let bedrock = BedrockServer::new(server.clone(), DEFAULT_PORT, DEFAULT_PROTOCOL_VERSION);
+
+        for id in [UNCONNECTED_PING, UNCONNECTED_PING_OPEN_CONNECTIONS] {
+            let response = bedrock.handle(&ping(id), SOURCE.parse().unwrap()).unwrap();
+            assert_eq!(response[0], UNCONNECTED_PONG);
+            assert_eq!(response[1..9], 0x0102030405060708_i64.to_be_bytes());
+            assert_eq!(response[9..17], bedrock.guid.to_be_bytes());
+            assert_eq!(response[17..33], MAGIC);
+
+            let length = u16::from_be_bytes([response[33], response[34]]) as usize;
+            let server_id = std::str::from_utf8(&response[35..]).unwrap();
+            assert_eq!(server_id.len(), length);
+            assert_eq!(
+                server_id,
+                format!(
+                    "MCPE;A Minecraft Server;630;1.20.2;0;20;{};;Survival;1;19132;19132;",
+                    bedrock.guid
+                )
+            );
+        }
+        let metrics = server.metrics.lock().unwrap();
+        assert_eq!(metrics.bedrock_pings, 2);
+        assert_eq!(metrics.connections, 0);
+    }
+
+    #[test]
+    fn test_unconnected_ping_throttled() {
+        let mut server = Server::default();
+        let single = BucketConfig {
+            burst: 1,
+            per_second: 0.001,
+        };
+        server.rate_limiter =
+            Mutex::new(RateLimiter::new(None, None, Some(single), None, None, None));
+        let server = Arc::new(server);
+        let bedrock = BedrockServer::new(server.clone(), DEFAULT_PORT, DEFAULT_PROTOCOL_VERSION);
+
+        let source = SOURCE.parse().unwrap();
+        assert!(bedrock.handle(&ping(UNCONNECTED_PING), source).is_some());
+        assert_eq!(bedrock.handle(&ping(UNCONNECTED_PING), source), None);
+        // another address has its own bucket
+        assert!(bedrock
+            .handle(
+                &ping(UNCONNECTED_PING),
+                "198.51.100.42:54321".parse().unwrap()
+            )
+            .is_some());
+        assert_eq!(server.metrics.lock().unwrap().bedrock_pings, 2);
+    }
+
+    #[test]
+    fn test_server_id_description() {
+        let bedrock = BedrockServer::new(Arc::new(Server::default()), 19133, 630);
+        let mut profile = Profile::from_config(&config::Config::default());
+        profile.description = "Line 1;\nLine 2".to_string();
+        profile.players_online = 3;
+        assert_eq!(
+            bedrock.server_id(&profile),
+            format!(
+                "MCPE;Line 1 ;630;1.20.2;3;20;{};Line 2;Survival;1;19133;19133;",
+                bedrock.guid
+            )
+        );
+    }
+
+    #[test]
+    fn test_ignore() {
+        let bedrock = BedrockServer::new(Arc::new(Server::default()), DEFAULT_PORT, 630);
+        let source = SOURCE.parse().unwrap();
+
+        // Open Connection Request 1
+        let mut request = ping(UNCONNECTED_PING);
+        request[0] = 0x05;
+        assert_eq!(bedrock.handle(&request, source), None);
+
+        let mut request = ping(UNCONNECTED_PING);
+        request[9] = 0x01;
+        assert_eq!(bedrock.handle(&request, source), None);
+        assert_eq!(bedrock.handle(&request[..PING_LENGTH - 1], source), None);
+    }
+}
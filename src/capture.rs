@@ -0,0 +1,309 @@
+use crate::packet::c2s_encryption_response::C2SEncryptionResponse;
+use crate::packet::datatype::varint;
+use crate::packet::{Packet, PacketError, Result};
+use crate::server;
+use crate::session::{Session, SessionState};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+// one line of a capture file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    // milliseconds since the UNIX epoch
+    pub timestamp: u64,
+    pub direction: Direction,
+    pub state: String,
+    // the whole frame (length, id and body) before encryption, in hex
+    pub payload: String,
+}
+
+// writes the frames of a session as JSON lines
+pub struct Capture {
+    writer: Box<dyn Write + Send>,
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capture").finish_non_exhaustive()
+    }
+}
+
+impl Capture {
+    pub fn new(writer: Box<dyn Write + Send>) -> Capture {
+        Capture { writer }
+    }
+
+    // <directory>/<timestamp>-<ip>-<port>.jsonl
+    pub fn create(directory: &str, peer_address: &SocketAddr) -> io::Result<Capture> {
+        fs::create_dir_all(directory)?;
+        let name = format!(
+            "{}-{}-{}.jsonl",
+            now(),
+            peer_address.ip().to_string().replace(':', "_"),
+            peer_address.port()
+        );
+        let file: File = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(Path::new(directory).join(name))?;
+        Ok(Capture::new(Box::new(BufWriter::new(file))))
+    }
+
+    pub fn record(&mut self, direction: Direction, state: &SessionState, payload: &[u8]) {
+        let frame = Frame {
+            timestamp: now(),
+            direction,
+            state: state.to_string(),
+            payload: to_hex(payload),
+        };
+        let line = serde_json::to_string(&frame).unwrap_or_default();
+        if let Err(e) = writeln!(self.writer, "{}", line).and_then(|_| self.writer.flush()) {
+            warn!("Could not write capture: {}", e);
+        }
+    }
+}
+
+pub fn read_frames(reader: impl BufRead) -> Result<Vec<Frame>> {
+    let mut frames: Vec<Frame> = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Frame>(&line) {
+            Ok(frame) => frames.push(frame),
+            Err(e) => {
+                return Err(PacketError::ReadError(format!("Invalid capture line: {}", e)).into())
+            }
+        }
+    }
+    Ok(frames)
+}
+
+// feeds the inbound frames to a new session; its responses go to the session's own capture
+// only status and logins without encryption can be replayed, as the shared secret was
+// encrypted with a key pair that is not recorded
+pub fn replay(session: &mut Session, frames: &[Frame]) -> Result<MemoryStream> {
+    let mut input: VecDeque<u8> = VecDeque::new();
+    for frame in frames.iter().filter(|f| f.direction == Direction::Inbound) {
+        let Some(payload) = from_hex(&frame.payload) else {
+            return Err(
+                PacketError::ReadError(format!("Invalid payload: {}", frame.payload)).into(),
+            );
+        };
+        if frame.state == SessionState::LOGIN.to_string()
+            && packet_id(&payload) == Some(C2SEncryptionResponse::ID)
+        {
+            return Err(PacketError::SequenceError(
+                "Encrypted logins cannot be replayed".to_string(),
+            )
+            .into());
+        }
+        input.extend(payload);
+    }
+
+    let mut stream = MemoryStream {
+        input,
+        output: vec![],
+    };
+    server::handle_connection(session, &mut stream)?;
+    Ok(stream)
+}
+
+// a connection whose input is known in advance
+#[derive(Debug, Default)]
+pub struct MemoryStream {
+    pub input: VecDeque<u8>,
+    pub output: Vec<u8>,
+}
+
+impl Read for MemoryStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for MemoryStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn packet_id(mut payload: &[u8]) -> Option<i32> {
+    varint::read_from_stream(&mut payload).ok()?;
+    varint::read_from_stream(&mut payload)
+        .ok()
+        .map(|id| id.value)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn captured_session(buffer: &SharedBuffer) -> Session {
+        let mut session = Session::new(
+            "127.0.0.1:12345".parse().unwrap(),
+            Arc::new(Server::default()),
+        );
+        session.capture = Some(Mutex::new(Capture::new(Box::new(buffer.clone()))));
+        session
+    }
+
+    fn frames_of(buffer: &SharedBuffer) -> Vec<Frame> {
+        read_frames(&buffer.0.lock().unwrap()[..]).unwrap()
+    }
+
+    #[test]
+    fn test_capture_and_replay() {
+        // Handshake (765, "a", 25565, status), Status Request, Ping Request
+        let input = [
+            0x08, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x01, 0x01, 0x00, 0x09, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a,
+        ];
+        let buffer = SharedBuffer::default();
+        let mut session = captured_session(&buffer);
+        let mut stream = MemoryStream {
+            input: VecDeque::from(input),
+            output: vec![],
+        };
+        server::handle_connection(&mut session, &mut stream).unwrap();
+
+        let frames = frames_of(&buffer);
+        let summary: Vec<(Direction, &str)> = frames
+            .iter()
+            .map(|f| (f.direction, f.state.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Direction::Inbound, "HANDSHAKING"),
+                (Direction::Inbound, "STATUS"),
+                (Direction::Outbound, "STATUS"),
+                (Direction::Inbound, "STATUS"),
+                (Direction::Outbound, "STATUS"),
+            ]
+        );
+        assert_eq!(frames[0].payload, "0800fd05016163dd01");
+        assert_eq!(frames[4].payload, "0901000000000000002a");
+
+        // replaying the capture gives the same responses
+        let replay_buffer = SharedBuffer::default();
+        let mut replay_session = captured_session(&replay_buffer);
+        let replayed = replay(&mut replay_session, &frames).unwrap();
+        assert_eq!(replayed.output, stream.output);
+        let payloads =
+            |frames: Vec<Frame>| -> Vec<String> { frames.into_iter().map(|f| f.payload).collect() };
+        assert_eq!(payloads(frames_of(&replay_buffer)), payloads(frames));
+    }
+
+    #[test]
+    fn test_replay_login() {
+        // Handshake (765, "a", 25565, login), Login Start ("Notch", UUID)
+        let input = [
+            vec![0x08, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x02],
+            vec![0x17, 0x00, 0x05, 0x4e, 0x6f, 0x74, 0x63, 0x68],
+            0x069a79f444e94726a5befca90e38aaf5u128
+                .to_be_bytes()
+                .to_vec(),
+        ]
+        .concat();
+        let offline_session = |buffer: &SharedBuffer| {
+            let mut session = captured_session(buffer);
+            let mut server = Server::default();
+            server.virtual_hosts.get_mut().unwrap().default.online_mode = false;
+            session.server = Arc::new(server);
+            session
+        };
+        let buffer = SharedBuffer::default();
+        let mut session = offline_session(&buffer);
+        let mut stream = MemoryStream {
+            input: VecDeque::from(input),
+            output: vec![],
+        };
+        server::handle_connection(&mut session, &mut stream).unwrap();
+        let frames = frames_of(&buffer);
+        assert_eq!(frames.last().unwrap().direction, Direction::Outbound);
+
+        // kicked with the same disconnect message
+        let replay_buffer = SharedBuffer::default();
+        let mut replay_session = offline_session(&replay_buffer);
+        let replayed = replay(&mut replay_session, &frames).unwrap();
+        assert_eq!(replayed.output, stream.output);
+        assert_eq!(replay_session.username, Some("Notch".to_string()));
+
+        // the Encryption Response of an online-mode login cannot be decrypted
+        let mut frames = frames[..2].to_vec();
+        frames.push(Frame {
+            timestamp: 0,
+            direction: Direction::Inbound,
+            state: "LOGIN".to_string(),
+            payload: "0501000100".to_string(),
+        });
+        let mut replay_session = captured_session(&SharedBuffer::default());
+        assert!(replay(&mut replay_session, &frames).is_err_and(|e| e
+            .to_string()
+            .contains("Encrypted logins cannot be replayed")));
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x00, 0x7f, 0xff]), "007fff");
+        assert_eq!(from_hex("007fff"), Some(vec![0x00, 0x7f, 0xff]));
+        assert_eq!(from_hex("007"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
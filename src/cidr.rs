@@ -0,0 +1,124 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::{error, fmt};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix_length: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        let address = match address {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(*address, IpAddr::V4),
+            IpAddr::V4(_) => *address,
+        };
+
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_length as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_length as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    // a bare address is treated as a single host
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_length) = match s.split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (s, None),
+        };
+
+        let address = match IpAddr::from_str(address.trim()) {
+            Ok(a) => a,
+            Err(_) => return Err(CidrError(s.to_string())),
+        };
+        let max_prefix_length = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_length = match prefix_length {
+            Some(p) => match p.trim().parse::<u8>() {
+                Ok(p) if p <= max_prefix_length => p,
+                _ => return Err(CidrError(s.to_string())),
+            },
+            None => max_prefix_length,
+        };
+
+        Ok(Cidr {
+            address,
+            prefix_length,
+        })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_length)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CidrError(String);
+
+impl fmt::Display for CidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid CIDR: {}", self.0)
+    }
+}
+
+impl error::Error for CidrError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            Cidr::from_str("10.0.0.0/8").unwrap().to_string(),
+            "10.0.0.0/8"
+        );
+        assert_eq!(
+            Cidr::from_str("192.168.1.1").unwrap().to_string(),
+            "192.168.1.1/32"
+        );
+        assert_eq!(Cidr::from_str("::1").unwrap().to_string(), "::1/128");
+        assert!(Cidr::from_str("10.0.0.0/33").is_err());
+        assert!(Cidr::from_str("example.com").is_err());
+    }
+
+    #[test]
+    fn test_contains_v4() {
+        let cidr = Cidr::from_str("10.1.0.0/16").unwrap();
+        assert!(cidr.contains(&ip("10.1.2.3")));
+        assert!(!cidr.contains(&ip("10.2.0.1")));
+        assert!(cidr.contains(&ip("::ffff:10.1.255.255")));
+        assert!(Cidr::from_str("0.0.0.0/0")
+            .unwrap()
+            .contains(&ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn test_contains_v6() {
+        let cidr = Cidr::from_str("2001:db8::/32").unwrap();
+        assert!(cidr.contains(&ip("2001:db8:1::1")));
+        assert!(!cidr.contains(&ip("2001:db9::1")));
+        assert!(!cidr.contains(&ip("10.0.0.1")));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::packet::c2s_handshake::C2SHandshakePacket;
+use crate::packet::c2s_ping_request::C2SPingRequestPacket;
+use crate::packet::c2s_status_request::C2SStatusRequestPacket;
+use crate::packet::datatype::{long, string, unsigned_short, varint, Decode};
+use crate::packet::s2c_ping_response::S2CPingResponsePacket;
+use crate::packet::s2c_status_response::S2CStatusResponsePacket;
+use crate::packet::{self, registry, Packet, PacketError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_PORT: u16 = 25565;
+pub const DEFAULT_PROTOCOL_VERSION: i32 = registry::PROTOCOL_1_20_2;
+
+// https://wiki.vg/Server_List_Ping#Status_Response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusResponse {
+    pub version: StatusVersion,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub players: Option<StatusPlayers>,
+    // a text component, either a string or an object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    #[serde(rename = "enforcesSecureChat", skip_serializing_if = "Option::is_none")]
+    pub enforces_secure_chat: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusVersion {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusPlayers {
+    pub max: i64,
+    pub online: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<StatusPlayer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusPlayer {
+    pub name: String,
+    pub id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PingResult {
+    pub address: String,
+    pub status: StatusResponse,
+    // round trip of the ping request in milliseconds
+    pub latency: f64,
+}
+
+// "host", "host:port", "[::1]" or "[::1]:port"
+pub fn parse_address(address: &str) -> (String, u16) {
+    if let Some(rest) = address.strip_prefix('[') {
+        if let Some((host, rest)) = rest.split_once(']') {
+            let port = rest.strip_prefix(':').and_then(|p| p.parse().ok());
+            return (host.to_string(), port.unwrap_or(DEFAULT_PORT));
+        }
+    }
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => match port.parse() {
+            Ok(port) => (host.to_string(), port),
+            Err(_) => (address.to_string(), DEFAULT_PORT),
+        },
+        _ => (address.to_string(), DEFAULT_PORT),
+    }
+}
+
+pub fn ping(host: &str, port: u16, protocol_version: i32, timeout: Duration) -> Result<PingResult> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| PacketError::ReadError(format!("Could not resolve {}:{}", host, port)))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let (status, latency) = ping_stream(&mut stream, host, port, protocol_version)?;
+    Ok(PingResult {
+        address: address.to_string(),
+        status,
+        latency: latency.as_secs_f64() * 1000.0,
+    })
+}
+
+// handshake with next state 1, status request and ping request over an open connection
+pub fn ping_stream(
+    stream: &mut (impl Read + Write),
+    host: &str,
+    port: u16,
+    protocol_version: i32,
+) -> Result<(StatusResponse, Duration)> {
+    stream.write_all(&packet::encode_packet(
+        C2SHandshakePacket::ID,
+        &[
+            &varint::VarInt::from(protocol_version),
+            &string::String::from(host),
+            &unsigned_short::UnsignedShort::from(port),
+            &varint::VarInt::from(1),
+        ],
+    ))?;
+    stream.write_all(&packet::encode_packet(C2SStatusRequestPacket::ID, &[]))?;
+
+    let mut body = read_packet(stream, S2CStatusResponsePacket::ID)?;
+    let response_json = string::String::decode(&mut body)?;
+    let status = match serde_json::from_str::<StatusResponse>(&response_json.value) {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(PacketError::ReadError(format!("Invalid status response: {}", e)).into())
+        }
+    };
+
+    let payload = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let start = Instant::now();
+    stream.write_all(&packet::encode_packet(
+        C2SPingRequestPacket::ID,
+        &[&long::Long::from(payload)],
+    ))?;
+    let mut body = read_packet(stream, S2CPingResponsePacket::ID)?;
+    let latency = start.elapsed();
+
+    let echoed = long::Long::decode(&mut body)?;
+    if echoed.value != payload {
+        return Err(PacketError::SequenceError(format!(
+            "Ping response payload does not match: {}",
+            echoed.value
+        ))
+        .into());
+    }
+
+    Ok((status, latency))
+}
+
+// reads a whole client-bound packet and returns the body following its id
+fn read_packet(stream: &mut impl Read, expected_id: i32) -> Result<VecDeque<u8>> {
+    let length = varint::VarInt::decode(stream)?;
+    if length.value < 1 || length.value > packet::MAX_PACKET_LENGTH {
+        return Err(
+            PacketError::ReadError(format!("Invalid packet length: {}", length.value)).into(),
+        );
+    }
+    let body = packet::read_byte_array(stream, length.value)?;
+
+    let mut body = VecDeque::from(body);
+    let id = varint::VarInt::decode(&mut body)?;
+    if id.value != expected_id {
+        return Err(
+            PacketError::SequenceError(format!("Unexpected packet id: {}", id.value)).into(),
+        );
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{self, Server};
+    use crate::session::Session;
+    use serde_json::json;
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+    use std::thread;
+
+    // serves a single connection with the default configuration
+    fn serve_once() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, peer_address) = listener.accept().unwrap();
+            let mut session = Session::new(peer_address, Arc::new(Server::default()));
+            server::handle_connection(&mut session, &mut stream).unwrap();
+        });
+        address
+    }
+
+    #[test]
+    fn test_ping() {
+        let address = serve_once();
+        let result = ping(
+            "127.0.0.1",
+            address.port(),
+            DEFAULT_PROTOCOL_VERSION,
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        assert_eq!(result.address, address.to_string());
+        assert_eq!(
+            result.status.version,
+            StatusVersion {
+                name: "1.20.2".to_string(),
+                protocol: 764
+            }
+        );
+        assert_eq!(
+            result.status.description,
+            Some(json!({"text": "A Minecraft Server"}))
+        );
+        assert_eq!(
+            result.status.players,
+            Some(StatusPlayers {
+                max: 20,
+                online: 0,
+                sample: vec![]
+            })
+        );
+        assert!(result.latency >= 0.0);
+    }
+
+    #[test]
+    fn test_status_response() {
+        // as sent by a vanilla server
+        let status: StatusResponse = serde_json::from_str(
+            r#"{"version":{"name":"1.20.4","protocol":765},"players":{"max":20,"online":1,"sample":[{"name":"thinkofdeath","id":"4566e69f-c907-48ee-8d71-d7ba5aa00d20"}]},"description":"Hello world","enforcesSecureChat":true,"previewsChat":true}"#,
+        )
+        .unwrap();
+        assert_eq!(status.version.protocol, 765);
+        assert_eq!(status.players.unwrap().sample[0].name, "thinkofdeath");
+        assert_eq!(status.description, Some(json!("Hello world")));
+        assert_eq!(status.enforces_secure_chat, Some(true));
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("mc.example.com"),
+            ("mc.example.com".to_string(), 25565)
+        );
+        assert_eq!(
+            parse_address("mc.example.com:25566"),
+            ("mc.example.com".to_string(), 25566)
+        );
+        assert_eq!(parse_address("[::1]:25566"), ("::1".to_string(), 25566));
+        assert_eq!(parse_address("[::1]"), ("::1".to_string(), 25565));
+        assert_eq!(parse_address("::1"), ("::1".to_string(), 25565));
+    }
+}
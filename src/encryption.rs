@@ -72,11 +72,7 @@ fn get_hex_digest(bytes: &[u8; 20]) -> String {
     num_bigint::BigInt::from_signed_bytes_be(bytes).to_str_radix(16)
 }
 
-pub fn authenticate(
-    shared_secret: &Vec<u8>,
-    pkey_in_der: &Vec<u8>,
-    username: &String,
-) -> Result<()> {
+pub fn authenticate(shared_secret: &[u8], pkey_in_der: &[u8], username: &String) -> Result<()> {
     let mut hasher = openssl::sha::Sha1::new();
     hasher.update(shared_secret);
     hasher.update(pkey_in_der);
@@ -90,6 +86,33 @@ pub fn authenticate(
     Ok(())
 }
 
+pub fn verify_hmac_sha256(key: &[u8], data: &[u8], signature: &[u8]) -> Result<bool> {
+    let pkey = match openssl::pkey::PKey::hmac(key) {
+        Ok(p) => p,
+        Err(e) => {
+            return Err(EncryptionError::new(format!("Failed to create HMAC key: {}", e)).into())
+        }
+    };
+    let mut signer = match openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &pkey)
+    {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(EncryptionError::new(format!("Failed to create HMAC signer: {}", e)).into())
+        }
+    };
+    if let Err(e) = signer.update(data) {
+        return Err(EncryptionError::new(format!("Failed to compute HMAC: {}", e)).into());
+    }
+    let hmac = match signer.sign_to_vec() {
+        Ok(h) => h,
+        Err(e) => {
+            return Err(EncryptionError::new(format!("Failed to compute HMAC: {}", e)).into())
+        }
+    };
+
+    Ok(hmac.len() == signature.len() && openssl::memcmp::eq(&hmac, signature))
+}
+
 #[derive(Debug)]
 pub struct EncryptionError {
     reason: String,
@@ -111,7 +134,7 @@ impl error::Error for EncryptionError {}
 
 #[cfg(test)]
 mod tests {
-    use crate::encryption::get_hex_digest;
+    use crate::encryption::{get_hex_digest, verify_hmac_sha256};
 
     #[test]
     fn test_get_hex_digest() {
@@ -140,4 +163,18 @@ mod tests {
             "88e16a1019277b15d58faf0541e11910eb756f6"
         );
     }
+
+    #[test]
+    fn test_verify_hmac_sha256() {
+        // RFC 4231 test case 2
+        let signature: [u8; 32] = [
+            0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95,
+            0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9,
+            0x64, 0xec, 0x38, 0x43,
+        ];
+        let data = "what do ya want for nothing?".as_bytes();
+        assert!(verify_hmac_sha256("Jefe".as_bytes(), data, &signature).unwrap());
+        assert!(!verify_hmac_sha256("jefe".as_bytes(), data, &signature).unwrap());
+        assert!(!verify_hmac_sha256("Jefe".as_bytes(), data, &signature[..16]).unwrap());
+    }
 }
@@ -0,0 +1,199 @@
+use crate::session::Session;
+use config::Config;
+use log::{info, warn};
+use serde::Deserialize;
+use std::fs;
+use std::net::IpAddr;
+
+const DEFAULT_RULES_FILE: &str = "fingerprint-rules.json";
+pub const UNKNOWN_KIND: &str = "unknown";
+
+// used when the rule file does not exist
+const DEFAULT_RULES: &str = r#"[
+    {"kind": "scanner", "protocol_versions": [-1]},
+    {"kind": "mcstatus", "protocol_versions": [47], "state": "STATUS"},
+    {"kind": "scanner", "state": "STATUS", "status_requested": false},
+    {"kind": "scanner", "state": "STATUS", "ip_literal": true, "pinged": false},
+    {"kind": "scanner", "state": "STATUS", "expected_port": false, "pinged": false},
+    {"kind": "vanilla", "state": "STATUS", "pinged": true},
+    {"kind": "vanilla", "state": "LOGIN"}
+]"#;
+
+// every condition that is present has to hold
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Rule {
+    pub kind: String,
+    // any of these
+    #[serde(default)]
+    pub protocol_versions: Vec<i32>,
+    // HANDSHAKING, STATUS or LOGIN at the end of the session
+    pub state: Option<String>,
+    // the server address is an IP address instead of a host name
+    pub ip_literal: Option<bool>,
+    // the server port is the port this server listens on
+    pub expected_port: Option<bool>,
+    pub status_requested: Option<bool>,
+    pub pinged: Option<bool>,
+}
+
+impl Rule {
+    fn matches(&self, session: &Session, port: u16) -> bool {
+        let checks = [
+            self.protocol_versions.is_empty()
+                || session
+                    .protocol_version
+                    .is_some_and(|v| self.protocol_versions.contains(&v)),
+            self.state
+                .as_ref()
+                .is_none_or(|s| s.eq_ignore_ascii_case(&session.state.to_string())),
+            self.ip_literal
+                .is_none_or(|v| v == is_ip_literal(session.server_address.as_deref())),
+            self.expected_port
+                .is_none_or(|v| v == (session.server_port == Some(port))),
+            self.status_requested
+                .is_none_or(|v| v == session.status_requested),
+            self.pinged.is_none_or(|v| v == session.pinged),
+        ];
+        checks.iter().all(|c| *c)
+    }
+}
+
+#[derive(Debug)]
+pub struct Fingerprinter {
+    rules: Vec<Rule>,
+    port: u16,
+}
+
+impl Fingerprinter {
+    pub fn new(rules: Vec<Rule>, port: u16) -> Fingerprinter {
+        Fingerprinter { rules, port }
+    }
+
+    pub fn from_config(config: &Config) -> Fingerprinter {
+        let path = config
+            .get::<String>("fingerprint_rules_file")
+            .unwrap_or(DEFAULT_RULES_FILE.into());
+        let port = config.get::<u16>("port").unwrap_or(25565);
+
+        let rules = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<Vec<Rule>>(&content) {
+                Ok(rules) => {
+                    info!("Loaded {} fingerprint rules", rules.len());
+                    rules
+                }
+                Err(e) => {
+                    warn!("Could not parse {}: {}", path, e);
+                    vec![]
+                }
+            },
+            Err(_) => serde_json::from_str(DEFAULT_RULES).unwrap_or_default(),
+        };
+        Fingerprinter::new(rules, port)
+    }
+
+    // the kind of the first matching rule
+    pub fn classify(&self, session: &Session) -> String {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(session, self.port))
+            .map(|rule| rule.kind.clone())
+            .unwrap_or(UNKNOWN_KIND.to_string())
+    }
+}
+
+impl Default for Fingerprinter {
+    fn default() -> Self {
+        Fingerprinter::new(
+            serde_json::from_str(DEFAULT_RULES).unwrap_or_default(),
+            25565,
+        )
+    }
+}
+
+// Forge and BungeeCord append their data after a null character
+fn is_ip_literal(server_address: Option<&str>) -> bool {
+    let Some(address) = server_address else {
+        return false;
+    };
+    let host = address.split('\0').next().unwrap_or_default();
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.parse::<IpAddr>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use crate::session::SessionState;
+    use std::sync::Arc;
+
+    fn session(protocol_version: i32, server_address: &str, server_port: u16) -> Session {
+        let mut session = Session::new(
+            "127.0.0.1:12345".parse().unwrap(),
+            Arc::new(Server::default()),
+        );
+        session.protocol_version = Some(protocol_version);
+        session.server_address = Some(server_address.to_string());
+        session.server_port = Some(server_port);
+        session.state = SessionState::STATUS;
+        session
+    }
+
+    #[test]
+    fn test_classify_default_rules() {
+        let fingerprinter = Fingerprinter::default();
+
+        let mut vanilla = session(764, "mc.example.com", 25565);
+        vanilla.status_requested = true;
+        vanilla.pinged = true;
+        assert_eq!(fingerprinter.classify(&vanilla), "vanilla");
+
+        let mut masscan = session(-1, "203.0.113.7", 25565);
+        masscan.status_requested = true;
+        assert_eq!(fingerprinter.classify(&masscan), "scanner");
+
+        let mut mcstatus = session(47, "mc.example.com", 25565);
+        mcstatus.status_requested = true;
+        assert_eq!(fingerprinter.classify(&mcstatus), "mcstatus");
+
+        // no ping after the status of an IP literal
+        let mut bot = session(764, "[2001:db8::1]", 25565);
+        bot.status_requested = true;
+        assert_eq!(fingerprinter.classify(&bot), "scanner");
+
+        // the handshake only
+        let handshake = session(764, "mc.example.com", 25565);
+        assert_eq!(fingerprinter.classify(&handshake), "scanner");
+
+        let mut odd_port = session(764, "mc.example.com", 1);
+        odd_port.status_requested = true;
+        assert_eq!(fingerprinter.classify(&odd_port), "scanner");
+
+        let mut login = session(764, "mc.example.com", 25565);
+        login.state = SessionState::LOGIN;
+        assert_eq!(fingerprinter.classify(&login), "vanilla");
+    }
+
+    #[test]
+    fn test_classify_rule_file() {
+        let rules: Vec<Rule> = serde_json::from_str(
+            r#"[{"kind": "old", "protocol_versions": [47, 340], "state": "login"}]"#,
+        )
+        .unwrap();
+        let fingerprinter = Fingerprinter::new(rules, 25565);
+
+        let mut old = session(340, "mc.example.com", 25565);
+        assert_eq!(fingerprinter.classify(&old), UNKNOWN_KIND);
+        old.state = SessionState::LOGIN;
+        assert_eq!(fingerprinter.classify(&old), "old");
+    }
+
+    #[test]
+    fn test_is_ip_literal() {
+        assert!(is_ip_literal(Some("127.0.0.1")));
+        assert!(is_ip_literal(Some("::1")));
+        assert!(is_ip_literal(Some("203.0.113.7\0FML3\0")));
+        assert!(!is_ip_literal(Some("mc.example.com")));
+        assert!(!is_ip_literal(None));
+    }
+}
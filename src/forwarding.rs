@@ -0,0 +1,150 @@
+use config::Config;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::{error, fmt};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ForwardingMode {
+    None,
+    BungeeCord,
+    Velocity,
+}
+
+impl ForwardingMode {
+    pub fn from_config(config: &Config) -> ForwardingMode {
+        let mode = config
+            .get::<String>("forwarding_mode")
+            .unwrap_or_default()
+            .to_lowercase();
+        match &mode[..] {
+            "bungeecord" | "legacy" => ForwardingMode::BungeeCord,
+            "velocity" | "modern" => ForwardingMode::Velocity,
+            _ => ForwardingMode::None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ForwardedPlayer {
+    pub server_address: String,
+    pub address: IpAddr,
+    pub uuid: u128,
+    pub properties: Vec<ProfileProperty>,
+}
+
+// host\0ip\0uuid[\0properties]
+pub fn parse_bungeecord(server_address: &str) -> Result<ForwardedPlayer, ForwardingError> {
+    let fields: Vec<&str> = server_address.split('\0').collect();
+    if fields.len() < 3 {
+        return Err(ForwardingError(
+            "If you wish to use IP forwarding, please enable it in your BungeeCord config as well!"
+                .to_string(),
+        ));
+    }
+
+    let address = match IpAddr::from_str(fields[1]) {
+        Ok(a) => a,
+        Err(_) => {
+            return Err(ForwardingError(format!(
+                "Invalid forwarded address: {}",
+                fields[1]
+            )))
+        }
+    };
+    let uuid_hex = fields[2].replace('-', "");
+    let uuid = match u128::from_str_radix(&uuid_hex, 16) {
+        Ok(u) if uuid_hex.len() == 32 => u,
+        _ => {
+            return Err(ForwardingError(format!(
+                "Invalid forwarded UUID: {}",
+                fields[2]
+            )))
+        }
+    };
+    let properties = match fields.get(3) {
+        Some(json) => match serde_json::from_str::<Vec<ProfileProperty>>(json) {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(ForwardingError(format!(
+                    "Invalid forwarded properties: {}",
+                    e
+                )))
+            }
+        },
+        None => vec![],
+    };
+
+    Ok(ForwardedPlayer {
+        server_address: fields[0].to_string(),
+        address,
+        uuid,
+        properties,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ForwardingError(pub String);
+
+impl fmt::Display for ForwardingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Forwarding error: {}", self.0)
+    }
+}
+
+impl error::Error for ForwardingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bungeecord() {
+        let forwarded = parse_bungeecord(
+            "mc.example.com\u{0}203.0.113.7\u{0}069a79f444e94726a5befca90e38aaf5\u{0}[{\"name\":\"textures\",\"value\":\"abc\",\"signature\":\"def\"}]",
+        )
+        .unwrap();
+        assert_eq!(forwarded.server_address, "mc.example.com");
+        assert_eq!(forwarded.address, IpAddr::from_str("203.0.113.7").unwrap());
+        assert_eq!(forwarded.uuid, 0x069a79f444e94726a5befca90e38aaf5);
+        assert_eq!(
+            forwarded.properties,
+            vec![ProfileProperty {
+                name: "textures".to_string(),
+                value: "abc".to_string(),
+                signature: Some("def".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_bungeecord_without_properties() {
+        let forwarded =
+            parse_bungeecord("mc.example.com\u{0}::1\u{0}069a79f4-44e9-4726-a5be-fca90e38aaf5")
+                .unwrap();
+        assert_eq!(forwarded.address, IpAddr::from_str("::1").unwrap());
+        assert_eq!(forwarded.uuid, 0x069a79f444e94726a5befca90e38aaf5);
+        assert!(forwarded.properties.is_empty());
+    }
+
+    #[test]
+    fn test_parse_bungeecord_invalid() {
+        assert!(parse_bungeecord("mc.example.com").is_err());
+        assert!(parse_bungeecord(
+            "mc.example.com\u{0}not-an-ip\u{0}069a79f444e94726a5befca90e38aaf5"
+        )
+        .is_err());
+        assert!(parse_bungeecord("mc.example.com\u{0}127.0.0.1\u{0}xyz").is_err());
+        assert!(parse_bungeecord(
+            "mc.example.com\u{0}127.0.0.1\u{0}069a79f444e94726a5befca90e38aaf5\u{0}{"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use config::Config;
+use log::{debug, info, warn};
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use serde::Deserialize;
+use std::fmt;
+use std::net::IpAddr;
+
+// the fields of the GeoIP2/GeoLite2 City, Country and ASN databases this server uses
+#[derive(Debug, Deserialize)]
+struct Record<'a> {
+    #[serde(borrow)]
+    country: Option<geoip2::country::Country<'a>>,
+    #[serde(borrow)]
+    city: Option<geoip2::city::City<'a>>,
+    autonomous_system_number: Option<u32>,
+    autonomous_system_organization: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Location {
+    // ISO 3166-1 alpha-2
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+    pub as_organization: Option<String>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "country: {}, city: {}, asn: {}",
+            self.country.as_deref().unwrap_or("-"),
+            self.city.as_deref().unwrap_or("-"),
+            self.asn.map(|a| format!("AS{}", a)).unwrap_or("-".into())
+        )?;
+        if let Some(organization) = &self.as_organization {
+            write!(f, " ({})", organization)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct GeoIp {
+    readers: Vec<Reader<Vec<u8>>>,
+}
+
+impl fmt::Debug for GeoIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeoIp")
+            .field("readers", &self.readers.len())
+            .finish()
+    }
+}
+
+impl GeoIp {
+    pub fn new(readers: Vec<Reader<Vec<u8>>>) -> GeoIp {
+        GeoIp { readers }
+    }
+
+    // a City (or Country) database and an ASN database, either of which may be omitted
+    pub fn from_config(config: &Config) -> GeoIp {
+        let readers = ["geoip_database", "geoip_asn_database"]
+            .iter()
+            .filter_map(|key| config.get::<String>(key).ok())
+            .filter_map(|path| match Reader::open_readfile(&path) {
+                Ok(reader) => {
+                    info!("Loaded {} ({})", path, reader.metadata.database_type);
+                    Some(reader)
+                }
+                Err(e) => {
+                    warn!("Could not open {}: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+        GeoIp::new(readers)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.readers.is_empty()
+    }
+
+    // the first database that has a field wins
+    pub fn lookup(&self, ip: &IpAddr) -> Location {
+        let mut location = Location::default();
+        for reader in &self.readers {
+            let record = match reader.lookup::<Record>(*ip) {
+                Ok(r) => r,
+                Err(MaxMindDBError::AddressNotFoundError(_)) => continue,
+                Err(e) => {
+                    debug!("Could not look up {}: {}", ip, e);
+                    continue;
+                }
+            };
+
+            if location.country.is_none() {
+                location.country = record
+                    .country
+                    .and_then(|c| c.iso_code)
+                    .map(|c| c.to_string());
+            }
+            if location.city.is_none() {
+                location.city = record
+                    .city
+                    .and_then(|c| c.names)
+                    .and_then(|names| names.get("en").map(|n| n.to_string()));
+            }
+            if location.asn.is_none() {
+                location.asn = record.autonomous_system_number;
+                location.as_organization =
+                    record.autonomous_system_organization.map(|o| o.to_string());
+            }
+        }
+        location
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::MemoryStream;
+    use crate::forwarding::ForwardingMode;
+    use crate::packet;
+    use crate::packet::datatype::{string, unsigned_short, varint};
+    use crate::server::{self, Server};
+    use crate::session::Session;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut bytes = match s.len() {
+            n if n < 29 => vec![0x40 | n as u8],
+            n => vec![0x40 | 29, (n - 29) as u8],
+        };
+        bytes.extend_from_slice(s.as_bytes());
+        bytes
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = vec![0xe0 | entries.len() as u8];
+        for (key, value) in entries {
+            bytes.extend(string(key));
+            bytes.extend_from_slice(value);
+        }
+        bytes
+    }
+
+    fn uint16(v: u16) -> Vec<u8> {
+        vec![0xa2, (v >> 8) as u8, v as u8]
+    }
+
+    // an IPv4 database with one record for 128.0.0.0/1
+    fn database(record: Vec<u8>) -> Reader<Vec<u8>> {
+        // node 0: 0.0.0.0/1 is empty (= node count), 128.0.0.0/1 points to the record
+        let mut bytes = vec![0x00, 0x00, 0x01, 0x00, 0x00, 0x11];
+        bytes.extend([0; 16]);
+        bytes.extend(record);
+        bytes.extend(b"\xab\xcd\xefMaxMind.com");
+        bytes.extend(map(&[
+            ("binary_format_major_version", uint16(2)),
+            ("binary_format_minor_version", uint16(0)),
+            ("build_epoch", vec![0x00, 0x02]),
+            ("database_type", string("Test")),
+            ("description", map(&[])),
+            ("ip_version", uint16(4)),
+            ("languages", vec![0x00, 0x04]),
+            ("node_count", vec![0xc1, 0x01]),
+            ("record_size", uint16(24)),
+        ]));
+        Reader::from_source(bytes).unwrap()
+    }
+
+    fn city_database() -> Reader<Vec<u8>> {
+        database(map(&[
+            ("country", map(&[("iso_code", string("JP"))])),
+            ("city", map(&[("names", map(&[("en", string("Tokyo"))]))])),
+        ]))
+    }
+
+    fn asn_database() -> Reader<Vec<u8>> {
+        database(map(&[
+            ("autonomous_system_number", vec![0xc2, 0xfb, 0xf0]),
+            ("autonomous_system_organization", string("Example")),
+        ]))
+    }
+
+    #[test]
+    fn test_lookup() {
+        let geoip = GeoIp::new(vec![city_database(), asn_database()]);
+        let location = geoip.lookup(&"203.0.113.7".parse().unwrap());
+        assert_eq!(
+            location,
+            Location {
+                country: Some("JP".to_string()),
+                city: Some("Tokyo".to_string()),
+                asn: Some(64496),
+                as_organization: Some("Example".to_string()),
+            }
+        );
+        assert_eq!(
+            location.to_string(),
+            "country: JP, city: Tokyo, asn: AS64496 (Example)"
+        );
+
+        // not in either database
+        let location = geoip.lookup(&"10.0.0.1".parse().unwrap());
+        assert_eq!(location, Location::default());
+        assert_eq!(location.to_string(), "country: -, city: -, asn: -");
+    }
+
+    #[test]
+    fn test_locate_forwarded_address() {
+        let server = Server {
+            forwarding_mode: ForwardingMode::BungeeCord,
+            geoip: GeoIp::new(vec![city_database()]),
+            ..Server::default()
+        };
+        // connected from BungeeCord, which is not in the database
+        let mut session = Session::new("10.0.0.1:12345".parse().unwrap(), Arc::new(server));
+        session.locate();
+        assert_eq!(session.location, Location::default());
+
+        let input = packet::encode_packet(
+            0x00,
+            &[
+                &varint::VarInt::from(765),
+                &string::String::from(format!("a\0203.0.113.7\0{:032x}", 1)),
+                &unsigned_short::UnsignedShort::from(25565),
+                &varint::VarInt::from(2),
+            ],
+        );
+        let mut stream = MemoryStream {
+            input: VecDeque::from(input),
+            output: vec![],
+        };
+        server::handle_connection(&mut session, &mut stream).unwrap();
+        assert_eq!(session.location.country, Some("JP".to_string()));
+    }
+}
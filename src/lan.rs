@@ -0,0 +1,70 @@
+use crate::server::Server;
+use log::{debug, info};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+// where the clients listen for "Open to LAN" worlds
+pub const MULTICAST_ADDRESS: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 2, 60), 4445);
+pub const INTERVAL: Duration = Duration::from_millis(1500);
+
+pub fn message(description: &str, port: u16) -> String {
+    format!("[MOTD]{}[/MOTD][AD]{}[/AD]", description, port)
+}
+
+// the description is read every time, so that changes made over RCON are announced as well
+pub fn announce(
+    socket: &UdpSocket,
+    server: &Server,
+    port: u16,
+    destination: SocketAddr,
+) -> io::Result<()> {
+    let description = server
+        .virtual_hosts
+        .read()
+        .unwrap()
+        .default
+        .description
+        .clone();
+    socket.send_to(message(&description, port).as_bytes(), destination)?;
+    Ok(())
+}
+
+pub fn run(socket: &UdpSocket, server: &Server, port: u16, destination: SocketAddr) {
+    info!("Announcing port {} to {}", port, destination);
+    loop {
+        if let Err(e) = announce(socket, server, port, destination) {
+            debug!("Could not announce to {}: {}", destination, e);
+        }
+        thread::sleep(INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announce() {
+        let receiver = UdpSocket::bind("0.0.0.0:0").unwrap();
+        receiver
+            .join_multicast_v4(MULTICAST_ADDRESS.ip(), &Ipv4Addr::LOCALHOST)
+            .unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let destination = SocketAddr::from((
+            *MULTICAST_ADDRESS.ip(),
+            receiver.local_addr().unwrap().port(),
+        ));
+
+        // the loopback interface is chosen by the source address
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        announce(&sender, &Server::default(), 25565, destination).unwrap();
+
+        let mut buf = [0; 256];
+        let n = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"[MOTD]A Minecraft Server[/MOTD][AD]25565[/AD]");
+    }
+}
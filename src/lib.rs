@@ -1 +1,21 @@
+pub mod ban_list;
+pub mod bedrock;
+pub mod capture;
+pub mod cidr;
+pub mod client;
 pub mod encryption;
+pub mod fingerprint;
+pub mod forwarding;
+pub mod geoip;
+pub mod lan;
+pub mod metrics;
+pub mod packet;
+pub mod passthrough;
+pub mod proxy_protocol;
+pub mod query;
+pub mod rate_limit;
+pub mod rcon;
+pub mod server;
+pub mod session;
+pub mod template;
+pub mod virtual_host;
@@ -1,26 +1,135 @@
-mod packet;
-mod session;
-
-use crate::session::Session;
 use config::Config;
 use env_logger::{Builder, Target::Stdout};
-use lazy_static::lazy_static;
+use fake_minecraft_server::bedrock::{self, BedrockServer};
+use fake_minecraft_server::capture::{self, Capture};
+use fake_minecraft_server::forwarding::ForwardingMode;
+use fake_minecraft_server::query::QueryServer;
+use fake_minecraft_server::rcon::RconServer;
+use fake_minecraft_server::server::{self, Server};
+use fake_minecraft_server::session::Session;
+use fake_minecraft_server::{client, lan, passthrough, proxy_protocol};
 use log::{debug, error, info, warn};
-use std::error;
-use std::net::{TcpListener, TcpStream};
-use std::process::Command;
+use serde_json::json;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::process::{self, Command};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: fake-minecraft-server [ping <host[:port]> [--protocol <version>] [--timeout <seconds>] | replay <capture file>]";
+
+// a client that stays silent this long is disconnected
+const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+// only trusted proxies send a PROXY header, so a slow one is not expected
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// the metrics file is rewritten at most this often instead of on every connection
+const METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+fn main() {
+    Builder::from_default_env().target(Stdout).init();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|a| a.as_str()) {
+        Some("ping") => process::exit(ping(&args[1..])),
+        Some("replay") => process::exit(replay(&args[1..])),
+        Some(command) => {
+            eprintln!("Unknown command: {}", command);
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+        None => serve(),
+    }
+}
+
+// prints the status of a server as JSON
+fn ping(args: &[String]) -> i32 {
+    let mut address = None;
+    let mut protocol_version = client::DEFAULT_PROTOCOL_VERSION;
+    let mut timeout = Duration::from_secs(5);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--protocol" => args
+                .next()
+                .and_then(|v| v.parse().ok())
+                .map(|v| protocol_version = v),
+            "--timeout" => args
+                .next()
+                .and_then(|v| v.parse().ok())
+                .map(|v| timeout = Duration::from_secs_f64(v)),
+            _ if address.is_none() => {
+                address = Some(client::parse_address(arg));
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid argument: {}", arg);
+            return 2;
+        }
+    }
+    let Some((host, port)) = address else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    match client::ping(&host, port, protocol_version, timeout) {
+        Ok(result) => {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap());
+            0
+        }
+        Err(e) => {
+            println!("{}", json!({ "error": e.to_string() }));
+            1
+        }
+    }
+}
+
+// feeds a capture to a session with the current configuration and prints the new capture
+fn replay(args: &[String]) -> i32 {
+    let [path] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let frames = match File::open(path)
+        .map_err(|e| e.into())
+        .and_then(|f| capture::read_frames(BufReader::new(f)))
+    {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", path, e);
+            return 1;
+        }
+    };
 
-lazy_static! {
-    static ref CONFIG: Config = Config::builder()
+    let server = Arc::new(Server::from_config(&load_config()));
+    let mut session = Session::new(SocketAddr::from(([127, 0, 0, 1], 0)), server);
+    session.capture = Some(Mutex::new(Capture::new(Box::new(io::stdout()))));
+    match capture::replay(&mut session, &frames) {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn load_config() -> Config {
+    Config::builder()
         .add_source(config::File::with_name("Config").required(false))
         .build()
-        .unwrap();
+        .unwrap()
 }
 
-fn main() {
-    Builder::from_default_env().target(Stdout).init();
+fn serve() {
+    let config = load_config();
 
-    let port = CONFIG.get::<u16>("port").unwrap_or(25565);
+    let port = config.get::<u16>("port").unwrap_or(25565);
     let full_address = format!("0.0.0.0:{}", port);
 
     let listener = match TcpListener::bind(&full_address) {
@@ -32,6 +141,42 @@ fn main() {
     };
     info!("Successfully listening on {}.", &full_address);
 
+    let server = Arc::new(Server::from_config(&config));
+    if server.proxy_protocol && server.proxy_protocol_trusted.is_empty() {
+        warn!("proxy_protocol is enabled, but no PROXY header is accepted without proxy_protocol_trusted");
+    }
+    let command = config.get::<Vec<String>>("command").ok();
+
+    if config.get::<bool>("enable_query").unwrap_or(false) {
+        let query_port = config.get::<u16>("query_port").unwrap_or(port);
+        start_query(server.clone(), query_port, port);
+    }
+    if config.get::<bool>("enable_bedrock").unwrap_or(false) {
+        let bedrock_port = config
+            .get::<u16>("bedrock_port")
+            .unwrap_or(bedrock::DEFAULT_PORT);
+        let protocol_version = config
+            .get::<i32>("bedrock_protocol_version")
+            .unwrap_or(bedrock::DEFAULT_PROTOCOL_VERSION);
+        start_bedrock(server.clone(), bedrock_port, protocol_version);
+    }
+    if config.get::<bool>("lan_announce").unwrap_or(false) {
+        start_lan_announcer(server.clone(), port);
+    }
+    if server.metrics_file.is_some() {
+        start_metrics_writer(server.clone());
+    }
+    if config.get::<bool>("enable_rcon").unwrap_or(false) {
+        let rcon_port = config.get::<u16>("rcon_port").unwrap_or(25575);
+        let password = config.get::<String>("rcon_password").unwrap_or_default();
+        start_rcon(server.clone(), rcon_port, &password);
+    }
+
+    accept_connections(&listener, server, command);
+}
+
+// every allowed session runs on its own thread, so that a silent client only holds its own
+fn accept_connections(listener: &TcpListener, server: Arc<Server>, command: Option<Vec<String>>) {
     for stream in listener.incoming() {
         info!("New connection");
         let mut stream = match stream {
@@ -41,69 +186,229 @@ fn main() {
                 continue;
             }
         };
-        let mut session = Session::new(&stream);
-        info!("[Start] {}", session.peer_address.to_string());
-
-        if let Err(e) = handle_connection(&mut session, &mut stream) {
+        let peer_address = match stream.peer_addr() {
+            Ok(a) => a,
+            Err(e) => {
+                error!("{}", e);
+                continue;
+            }
+        };
+        // set before anything is read, and shorter for the PROXY header as it is read here
+        if let Err(e) = stream
+            .set_read_timeout(Some(PROXY_HEADER_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(SESSION_TIMEOUT)))
+        {
             error!("{}", e);
             continue;
         }
+        let mut session = Session::new(peer_address, server.clone());
+        if let Err(e) = proxy_protocol::accept(&mut session, &mut stream) {
+            error!("{}", e);
+            continue;
+        }
+        if let Err(e) = stream.set_read_timeout(Some(SESSION_TIMEOUT)) {
+            error!("{}", e);
+            continue;
+        }
+        // checked against the address from the PROXY header, before any packet is read,
+        // so that idle and malformed connections are counted as well
+        let mut rate_limiter = server.rate_limiter.lock().unwrap();
+        if let Err(throttle) =
+            rate_limiter.check_connection(session.peer_address.ip(), Instant::now())
+        {
+            debug!(
+                "Throttled {} (connection, {}); {}",
+                session.peer_address, throttle, rate_limiter
+            );
+            continue;
+        }
+        drop(rate_limiter);
 
-        run_command(&session);
-        info!("[End] {}", session.peer_address.to_string());
+        // without forwarding the address is final here, so banned ones are dropped before any packet
+        if server.forwarding_mode == ForwardingMode::None {
+            let ban_list = server.ban_list.read().unwrap();
+            if let Some(reason) = ban_list.check_ip(&session.peer_address.ip()) {
+                info!(
+                    "Dropped {}: {}",
+                    session.peer_address,
+                    reason.replace('\n', " ")
+                );
+                continue;
+            }
+        }
+
+        let command = command.clone();
+        thread::spawn(move || run_session(session, stream, command.as_deref()));
     }
 }
 
-fn handle_connection(
-    session: &mut Session,
-    stream: &mut TcpStream,
-) -> Result<(), Box<dyn error::Error>> {
-    loop {
-        let header = packet::read_packet_header_from_stream(session, stream)?;
-        debug!("PacketHeader: {{{}}}", header);
+fn run_session(mut session: Session, mut stream: TcpStream, command: Option<&[String]>) {
+    let server = session.server.clone();
+    info!("[Start] {}", session.peer_address.to_string());
+    session.locate();
+
+    if let Some(capture_dir) = &server.capture_dir {
+        match Capture::create(capture_dir, &session.peer_address) {
+            Ok(c) => session.capture = Some(Mutex::new(c)),
+            Err(e) => warn!("Could not create capture in {}: {}", capture_dir, e),
+        }
+    }
+
+    let result = server::handle_connection(&mut session, &mut stream);
+    // counted where BungeeCord or Velocity forwarding says the player is
+    server.record_connection(&session.location);
+    let client_kind = server.fingerprinter.classify(&session);
+    info!("[Client] {} is {}", session.peer_address, client_kind);
+    session.client_kind = Some(client_kind);
+    if let Err(e) = result {
+        error!("{}", e);
+        return;
+    }
 
-        let body = packet::read_packet_body_from_stream(session, stream, &header)?;
-        debug!("PacketBody received");
+    if session.throttled {
+        return;
+    }
 
-        body.update_session(session);
-        body.respond(session, stream)?;
+    if let Some(command) = command {
+        run_command(command, &session);
+    }
 
-        // terminate
-        if session.next_packet_ids.len() == 0 {
-            break;
+    if session.passthrough_login_start.is_some() {
+        // spliced players may stay quiet for longer than a status ping or a login
+        let _ = stream.set_read_timeout(None);
+        if let Err(e) = passthrough::splice(&session, stream) {
+            error!("{}", e);
         }
     }
-    Ok(())
+    info!("[End] {}", session.peer_address.to_string());
 }
 
-fn run_command(session: &Session) {
-    let cmd_vec = match CONFIG.get::<Vec<String>>("command") {
-        Ok(c) => c,
-        Err(_) => {
+fn start_query(server: Arc<Server>, query_port: u16, port: u16) {
+    let query_address = format!("0.0.0.0:{}", query_port);
+    let socket = match UdpSocket::bind(&query_address) {
+        Ok(s) => s,
+        Err(e) => {
+            error!(
+                "Could not start the query listener on {}: {}",
+                &query_address, e
+            );
             return;
         }
     };
+    info!("Query listening on {}.", &query_address);
 
-    // replace variables
-    let mut replaced_args: Vec<String> = vec![];
-    for arg in &cmd_vec[1..] {
-        let replaced_arg: String = match &arg[..] {
-            "%peer_address%" => session.peer_address.to_string(),
-            "%username%" => session.username.clone().unwrap_or("%username%".to_string()),
-            "%uuid%" => {
-                if session.uuid.is_none() {
-                    "%uuid%".into()
-                } else {
-                    format!("{:x}", session.uuid.clone().unwrap())
-                }
+    thread::spawn(move || {
+        let mut query = QueryServer::new(server, "0.0.0.0", port);
+        if let Err(e) = query.run(&socket) {
+            error!("[Query] {}", e);
+        }
+    });
+}
+
+fn start_bedrock(server: Arc<Server>, bedrock_port: u16, protocol_version: i32) {
+    let bedrock_address = format!("0.0.0.0:{}", bedrock_port);
+    let socket = match UdpSocket::bind(&bedrock_address) {
+        Ok(s) => s,
+        Err(e) => {
+            error!(
+                "Could not start the Bedrock listener on {}: {}",
+                &bedrock_address, e
+            );
+            return;
+        }
+    };
+    info!("Bedrock listening on {}.", &bedrock_address);
+
+    thread::spawn(move || {
+        let bedrock = BedrockServer::new(server, bedrock_port, protocol_version);
+        if let Err(e) = bedrock.run(&socket) {
+            error!("[Bedrock] {}", e);
+        }
+    });
+}
+
+fn start_metrics_writer(server: Arc<Server>) {
+    thread::spawn(move || loop {
+        thread::sleep(METRICS_INTERVAL);
+        server.write_metrics();
+    });
+}
+
+fn start_lan_announcer(server: Arc<Server>, port: u16) {
+    let socket = match UdpSocket::bind("0.0.0.0:0") {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Could not start the LAN announcer: {}", e);
+            return;
+        }
+    };
+    thread::spawn(move || lan::run(&socket, &server, port, lan::MULTICAST_ADDRESS.into()));
+}
+
+fn start_rcon(server: Arc<Server>, rcon_port: u16, password: &str) {
+    if password.is_empty() {
+        warn!("RCON is not started because rcon_password is empty.");
+        return;
+    }
+    let rcon_address = format!("0.0.0.0:{}", rcon_port);
+    let listener = match TcpListener::bind(&rcon_address) {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Could not start RCON on {}: {}", &rcon_address, e);
+            return;
+        }
+    };
+    info!("RCON listening on {}.", &rcon_address);
+
+    let rcon = Arc::new(RconServer::new(server.clone(), password, load_config));
+    thread::spawn(move || accept_rcon(&listener, server, rcon));
+}
+
+// limited and timed out like game connections, as the port may be reachable before login
+fn accept_rcon(listener: &TcpListener, server: Arc<Server>, rcon: Arc<RconServer>) {
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                error!("[RCON] {}", e);
+                continue;
             }
-            "%state%" => session.state.to_string(),
-            "%is_authenticated%" => session.is_authenticated.to_string(),
-            _ => arg.clone(),
         };
+        let peer_address = match stream.peer_addr() {
+            Ok(a) => a,
+            Err(e) => {
+                error!("[RCON] {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = stream
+            .set_read_timeout(Some(SESSION_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(SESSION_TIMEOUT)))
+        {
+            error!("[RCON] {}", e);
+            continue;
+        }
+        let mut rate_limiter = server.rate_limiter.lock().unwrap();
+        if let Err(throttle) = rate_limiter.check_connection(peer_address.ip(), Instant::now()) {
+            debug!(
+                "[RCON] Throttled {} (connection, {}); {}",
+                peer_address, throttle, rate_limiter
+            );
+            continue;
+        }
+        drop(rate_limiter);
 
-        replaced_args.push(replaced_arg);
+        let rcon = rcon.clone();
+        thread::spawn(move || {
+            if let Err(e) = rcon.handle_connection(&mut stream, peer_address) {
+                error!("[RCON] {}: {}", peer_address, e);
+            }
+        });
     }
+}
+
+fn run_command(cmd_vec: &[String], session: &Session) {
+    let replaced_args = replace_variables(&cmd_vec[1..], session);
     info!("Run: {} {}", &cmd_vec[0], replaced_args.join(" "));
 
     // run
@@ -114,3 +419,154 @@ fn run_command(session: &Session) {
     info!("StdOut: {}", String::from_utf8_lossy(&out.stdout));
     warn!("StdErr: {}", String::from_utf8_lossy(&out.stderr));
 }
+
+fn replace_variables(args: &[String], session: &Session) -> Vec<String> {
+    let mut replaced_args: Vec<String> = vec![];
+    for arg in args {
+        let replaced_arg: String = match &arg[..] {
+            "%peer_address%" => session.peer_address.to_string(),
+            "%username%" => session.username.clone().unwrap_or("%username%".to_string()),
+            "%uuid%" => match session.uuid {
+                Some(uuid) => format!("{:x}", uuid),
+                None => "%uuid%".into(),
+            },
+            "%state%" => session.state.to_string(),
+            "%is_authenticated%" => session.is_authenticated.to_string(),
+            "%client_kind%" => session.client_kind.clone().unwrap_or_default(),
+            "%country%" => session.location.country.clone().unwrap_or_default(),
+            "%asn%" => session
+                .location
+                .asn
+                .map(|a| a.to_string())
+                .unwrap_or_default(),
+            _ => arg.clone(),
+        };
+
+        replaced_args.push(replaced_arg);
+    }
+    replaced_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_minecraft_server::ban_list::BanList;
+    use fake_minecraft_server::capture::MemoryStream;
+    use fake_minecraft_server::packet::datatype::varint;
+    use fake_minecraft_server::rate_limit::{BucketConfig, RateLimiter};
+    use std::collections::VecDeque;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_idle_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Arc::new(Server::default());
+        thread::spawn(move || accept_connections(&listener, server, None));
+
+        // connected, but never sends anything
+        let _idle = TcpStream::connect(address).unwrap();
+
+        // Handshake (765, "a", 25565, status), Status Request
+        let mut client = TcpStream::connect(address).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client
+            .write_all(&[
+                0x08, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x01, 0x01, 0x00,
+            ])
+            .unwrap();
+        // a Status Response, even though the first connection never goes away
+        let length = varint::read_from_stream(&mut client).unwrap();
+        assert!(length.value > 1);
+        assert_eq!(varint::read_from_stream(&mut client).unwrap().value, 0x00);
+    }
+
+    #[test]
+    fn test_banned_address_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Arc::new(Server::default());
+        // not written to the files
+        let mut ban_list = BanList::default();
+        ban_list.ban_ip("127.0.0.1", "test").unwrap();
+        *server.ban_list.write().unwrap() = ban_list;
+        thread::spawn(move || accept_connections(&listener, server, None));
+
+        // Handshake (765, "a", 25565, status), Status Request
+        let mut client = TcpStream::connect(address).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let _ = client.write_all(&[
+            0x08, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x01, 0x01, 0x00,
+        ]);
+        // closed without a response
+        let mut response = vec![];
+        let _ = client.read_to_end(&mut response);
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn test_rcon_throttled() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut server = Server::default();
+        let single = BucketConfig {
+            burst: 1,
+            per_second: 0.001,
+        };
+        server.rate_limiter =
+            Mutex::new(RateLimiter::new(Some(single), None, None, None, None, None));
+        let server = Arc::new(server);
+        let rcon = Arc::new(RconServer::new(server.clone(), "secret", load_config));
+        thread::spawn(move || accept_rcon(&listener, server, rcon));
+
+        // Login (request id 1, "secret")
+        let login = [
+            0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x73, 0x65,
+            0x63, 0x72, 0x65, 0x74, 0x00, 0x00,
+        ];
+        let mut first = TcpStream::connect(address).unwrap();
+        first
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        first.write_all(&login).unwrap();
+        // the auth response for request id 1
+        let mut response = [0; 14];
+        first.read_exact(&mut response).unwrap();
+        assert_eq!(response[4..8], [0x01, 0x00, 0x00, 0x00]);
+
+        // closed without a response
+        let mut second = TcpStream::connect(address).unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let _ = second.write_all(&login);
+        let mut response = vec![];
+        let _ = second.read_to_end(&mut response);
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn test_replace_variables_without_ping() {
+        let server = Arc::new(Server::default());
+        let mut session = Session::new("127.0.0.1:12345".parse().unwrap(), server.clone());
+        // Handshake (765, "a", 25565, status), then closed without a request or a ping
+        let mut stream = MemoryStream {
+            input: VecDeque::from([0x08, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x01]),
+            output: vec![],
+        };
+        server::handle_connection(&mut session, &mut stream).unwrap();
+        session.client_kind = Some(server.fingerprinter.classify(&session));
+
+        let args: Vec<String> = ["%peer_address%", "%state%", "%client_kind%", "%username%"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            replace_variables(&args, &session),
+            ["127.0.0.1:12345", "STATUS", "scanner", "%username%"]
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use crate::geoip::Location;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+const UNKNOWN_COUNTRY: &str = "unknown";
+
+// counted since the server started
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Metrics {
+    pub connections: u64,
+    // connections by country, including those that could not be located
+    pub countries: BTreeMap<String, u64>,
+    // counted apart, as a spoofed UDP flood needs no connection
+    pub bedrock_pings: u64,
+    // recorded since the file was last written
+    #[serde(skip)]
+    pub changed: bool,
+}
+
+impl Metrics {
+    pub fn record_connection(&mut self, location: &Location) {
+        self.connections += 1;
+        let country = location.country.as_deref().unwrap_or(UNKNOWN_COUNTRY);
+        *self.countries.entry(country.to_string()).or_default() += 1;
+        self.changed = true;
+    }
+
+    pub fn record_bedrock_ping(&mut self) {
+        self.bedrock_pings += 1;
+        self.changed = true;
+    }
+
+    // replaces the file so that it always holds the latest values
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).unwrap_or_default();
+        let temporary_path = format!("{}.tmp", path);
+        fs::write(&temporary_path, json)?;
+        fs::rename(&temporary_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_connection() {
+        let mut metrics = Metrics::default();
+        let japan = Location {
+            country: Some("JP".to_string()),
+            ..Default::default()
+        };
+        metrics.record_connection(&japan);
+        metrics.record_connection(&japan);
+        metrics.record_connection(&Location::default());
+        metrics.record_bedrock_ping();
+
+        assert_eq!(
+            serde_json::to_value(&metrics).unwrap(),
+            serde_json::json!({
+                "connections": 3,
+                "countries": {"JP": 2, "unknown": 1},
+                "bedrock_pings": 1,
+            })
+        );
+    }
+}
@@ -1,28 +1,38 @@
+use crate::capture::Direction;
 use crate::session::{Session, SessionState};
-use datatype::varint;
+use datatype::{varint, Decode, Encode};
+use log::debug;
 use std::error;
 use std::io::{Read, Write};
-use std::net::TcpStream;
 use std::{fmt, fmt::Debug};
 
 pub mod c2s_encryption_response;
 pub mod c2s_handshake;
+pub mod c2s_login_plugin_response;
 pub mod c2s_login_start;
 pub mod c2s_ping_request;
 pub mod c2s_status_request;
 pub mod s2c_disconnect;
 pub mod s2c_encryption_request;
+pub mod s2c_login_plugin_request;
 pub mod s2c_ping_response;
 pub mod s2c_status_response;
 
-mod datatype;
+pub mod registry;
+
+pub mod datatype;
 
 pub type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+// the length of a packet is a VarInt of at most 3 bytes
+pub const MAX_PACKET_LENGTH: i32 = 2097151;
+
 #[derive(Debug)]
 pub struct PacketHeader {
     pub length: i32,
     pub id: i32,
+    // the length without the packet id
+    pub body_length: i32,
 }
 
 impl fmt::Display for PacketHeader {
@@ -33,10 +43,18 @@ impl fmt::Display for PacketHeader {
 
 pub fn read_packet_header_from_stream(
     session: &mut Session,
-    stream: &mut TcpStream,
+    stream: &mut impl Read,
 ) -> Result<PacketHeader> {
-    let packet_length = varint::read_from_stream(stream)?;
-    let packet_id = varint::read_from_stream(stream)?;
+    let packet_length = varint::VarInt::decode(stream)?;
+    if packet_length.value < 1 || packet_length.value > MAX_PACKET_LENGTH {
+        return Err(PacketError::ReadError(format!(
+            "Invalid packet length: {}",
+            packet_length.value
+        ))
+        .into());
+    }
+    let mut limited_stream = stream.take(packet_length.value as u64);
+    let packet_id = varint::VarInt::decode(&mut limited_stream)?;
 
     if !session.next_packet_ids.contains(&packet_id.value) {
         return Err(PacketError::SequenceError(format!(
@@ -49,6 +67,7 @@ pub fn read_packet_header_from_stream(
     Ok(PacketHeader {
         length: packet_length.value,
         id: packet_id.value,
+        body_length: limited_stream.limit() as i32,
     })
 }
 
@@ -56,90 +75,199 @@ pub trait PacketBody: Debug {
     fn update_session(&self, session: &mut Session);
 }
 
-pub trait ServerBoundPacketBody: PacketBody {
+// implemented by #[derive(Packet)]
+pub trait Packet {
+    const ID: i32;
+    const MIN_PROTOCOL: i32;
+}
+
+// generated by #[derive(Packet)] unless the packet is marked `manual`
+pub trait ReadPacket {
     fn read_from_stream(
         session: &mut Session,
         stream: &mut impl Read,
-    ) -> Result<Box<dyn ServerBoundPacketBody>>
-    where
-        Self: Sized;
+    ) -> Result<Box<dyn ServerBoundPacketBody>>;
+}
 
-    fn respond(&self, session: &mut Session, stream: &mut TcpStream) -> Result<()>;
+pub trait ServerBoundPacketBody: PacketBody {
+    fn respond(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()>;
 }
 
 pub trait ClientBoundPacketBody: PacketBody {
-    fn write_to_stream(&self, session: &mut Session, stream: &mut impl Write) -> Result<()>;
+    fn write_to_stream(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()>;
+}
+
+// frames the fields as one packet and encrypts it once the shared secret has been exchanged
+pub fn write_packet<P: Packet>(
+    session: &Session,
+    stream: &mut dyn Write,
+    fields: &[&dyn Encode],
+) -> Result<()> {
+    if let Some(protocol_version) = session.protocol_version {
+        if protocol_version < P::MIN_PROTOCOL {
+            return Err(PacketError::SequenceError(format!(
+                "Packet 0x{:02x} is not supported by protocol version {}",
+                P::ID,
+                protocol_version
+            ))
+            .into());
+        }
+    }
+
+    let bytes = encode_packet(P::ID, fields);
+    session.record(Direction::Outbound, &bytes);
+    let bytes = match &session.shared_secret {
+        Some(shared_secret) => encrypt(shared_secret, &bytes)?,
+        None => bytes,
+    };
+
+    if stream.write_all(&bytes).is_err() {
+        return Err(PacketError::WriteError.into());
+    }
+
+    if stream.flush().is_err() {
+        return Err(PacketError::FlushError.into());
+    }
+
+    Ok(())
+}
+
+// length, id and fields, without encryption
+pub fn encode_packet(id: i32, fields: &[&dyn Encode]) -> Vec<u8> {
+    let mut body: Vec<u8> = Vec::new();
+    varint::VarInt::from(id).encode(&mut body);
+    for field in fields {
+        field.encode(&mut body);
+    }
+
+    let mut bytes: Vec<u8> = Vec::new();
+    varint::VarInt::from(body.len() as i32).encode(&mut bytes);
+    bytes.append(&mut body);
+    bytes
 }
 
+fn encrypt(shared_secret: &[u8], bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut ctx = match openssl::cipher_ctx::CipherCtx::new() {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            return Err(PacketError::EncryptionError(format!(
+                "Could not create CipherCtx instance: {}",
+                e
+            ))
+            .into());
+        }
+    };
+    if let Err(e) = ctx.encrypt_init(
+        Some(openssl::cipher::Cipher::aes_128_cfb8()),
+        Some(shared_secret),
+        Some(shared_secret),
+    ) {
+        return Err(PacketError::EncryptionError(format!(
+            "Could not initialize CipherCtx instance: {}",
+            e
+        ))
+        .into());
+    }
+    let mut encrypted_bytes: Vec<u8> = vec![];
+    if ctx.cipher_update_vec(bytes, &mut encrypted_bytes).is_err()
+        || ctx.cipher_final_vec(&mut encrypted_bytes).is_err()
+    {
+        return Err(PacketError::WriteError.into());
+    }
+    Ok(encrypted_bytes)
+}
+
+// malformed login packets are answered with a disconnect instead of a silently dropped socket
+pub fn kick_on_read_error(session: &mut Session, stream: &mut dyn Write, error: &dyn error::Error) {
+    if let SessionState::LOGIN = session.state {
+        let response_packet = s2c_disconnect::S2CDisconnectPacket::with_reason(format!(
+            "Internal Exception: {}",
+            error
+        ));
+        if let Err(e) = response_packet.write_to_stream(session, stream) {
+            debug!("Could not send disconnect: {}", e);
+        }
+    }
+}
+
+// keeps a copy of every byte read from the inner reader
+pub struct RecordingReader<'a, R: Read> {
+    pub inner: &'a mut R,
+    pub bytes: Vec<u8>,
+}
+
+impl<R: Read> Read for RecordingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+pub fn read_byte_array(stream: &mut impl Read, length: i32) -> Result<Vec<u8>> {
+    if length < 0 {
+        return Err(
+            PacketError::ReadError(format!("Invalid byte array length: {}", length)).into(),
+        );
+    }
+    // the length is not trusted for preallocation
+    let mut array: Vec<u8> = Vec::new();
+    match stream.take(length as u64).read_to_end(&mut array) {
+        Ok(n) if n == length as usize => Ok(array),
+        Ok(n) => Err(PacketError::ReadError(format!(
+            "Could not read byte array: expected {} bytes, got {}",
+            length, n
+        ))
+        .into()),
+        Err(e) => Err(PacketError::ReadError(format!("Could not read byte array: {}", e)).into()),
+    }
+}
+
+// the body is read through a reader limited to the declared length, so that a malformed packet
+// never consumes the bytes of the next one
 pub fn read_packet_body_from_stream(
     session: &mut Session,
-    stream: &mut TcpStream,
+    stream: &mut impl Read,
     header: &PacketHeader,
 ) -> Result<Box<dyn ServerBoundPacketBody>> {
-    return match session.state {
-        SessionState::HANDSHAKING => {
-            match header.id {
-                // 0x00
-                c2s_handshake::C2SHandshakePacket::PACKET_ID => {
-                    let packet =
-                        c2s_handshake::C2SHandshakePacket::read_from_stream(session, stream)?;
-                    Ok(packet)
-                }
-                _ => Err(
-                    PacketError::SequenceError(format!("Invalid packet id: {}", header.id)).into(),
-                ),
-            }
-        }
-        SessionState::STATUS => {
-            match header.id {
-                // 0x00
-                c2s_status_request::C2SStatusRequestPacket::PACKET_ID => {
-                    let packet = c2s_status_request::C2SStatusRequestPacket::read_from_stream(
-                        session, stream,
-                    )?;
-                    Ok(packet)
-                }
-                // 0x01
-                c2s_ping_request::C2SPingRequestPacket::PACKET_ID => {
-                    let packet =
-                        c2s_ping_request::C2SPingRequestPacket::read_from_stream(session, stream)?;
-                    Ok(packet)
-                }
-                _ => Err(
-                    PacketError::SequenceError(format!("Invalid packet id: {}", header.id)).into(),
-                ),
-            }
-        }
-        SessionState::LOGIN => {
-            match header.id {
-                // 0x00
-                c2s_login_start::C2SLoginStartPacket::PACKET_ID => {
-                    let packet =
-                        c2s_login_start::C2SLoginStartPacket::read_from_stream(session, stream)?;
-                    Ok(packet)
-                }
-                //0x01
-                c2s_encryption_response::C2SEncryptionResponse::PACKET_ID => {
-                    let packet = c2s_encryption_response::C2SEncryptionResponse::read_from_stream(
-                        session, stream,
-                    )?;
-                    Ok(packet)
-                }
-                _ => Err(
-                    PacketError::SequenceError(format!("Invalid packet id: {}", header.id)).into(),
-                ),
-            }
-        }
-    };
+    let registration =
+        registry::get_server_bound_packet(&session.state, session.protocol_version, header.id)
+            .ok_or_else(|| {
+                PacketError::SequenceError(format!("Invalid packet id: {}", header.id))
+            })?;
+
+    if header.length > registration.max_length {
+        return Err(PacketError::ReadError(format!(
+            "{} is too long: {} > {}",
+            registration.name, header.length, registration.max_length
+        ))
+        .into());
+    }
+
+    debug!("Reading {}", registration.name);
+    let mut limited_stream = stream.take(header.body_length as u64);
+    let body = (registration.read)(session, &mut limited_stream)?;
+
+    if limited_stream.limit() > 0 {
+        return Err(PacketError::TrailingBytes(format!(
+            "{} bytes left after {}",
+            limited_stream.limit(),
+            registration.name
+        ))
+        .into());
+    }
+    Ok(body)
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum PacketError {
     WriteError,
     FlushError,
     ReadError(String),
     SequenceError(String),
     EncryptionError(String),
+    TrailingBytes(String),
 }
 
 impl fmt::Display for PacketError {
@@ -150,8 +278,91 @@ impl fmt::Display for PacketError {
             PacketError::ReadError(s) => write!(f, "Read Error: {}", s),
             PacketError::SequenceError(s) => write!(f, "Sequence Error: {}", s),
             PacketError::EncryptionError(s) => write!(f, "Encryption Error: {}", s),
+            PacketError::TrailingBytes(s) => write!(f, "Trailing Bytes: {}", s),
         }
     }
 }
 
 impl error::Error for PacketError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    fn status_session() -> Session {
+        let mut session = Session::new(
+            "127.0.0.1:12345".parse().unwrap(),
+            Arc::new(Server::default()),
+        );
+        session.state = SessionState::STATUS;
+        session.protocol_version = Some(765);
+        session.next_packet_ids = &[0x00, 0x01];
+        session
+    }
+
+    fn read_packet(
+        session: &mut Session,
+        stream: &mut VecDeque<u8>,
+    ) -> Result<Box<dyn ServerBoundPacketBody>> {
+        let header = read_packet_header_from_stream(session, stream)?;
+        read_packet_body_from_stream(session, stream, &header)
+    }
+
+    #[test]
+    fn test_read_packet() {
+        let mut session = status_session();
+        // Status Request, Ping Request
+        let mut stream: VecDeque<u8> = VecDeque::from([
+            0x01, 0x00, 0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a,
+        ]);
+        assert!(read_packet(&mut session, &mut stream).is_ok());
+        assert!(read_packet(&mut session, &mut stream).is_ok());
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn test_read_packet_trailing_bytes() {
+        let mut session = status_session();
+        session.state = SessionState::HANDSHAKING;
+        session.next_packet_ids = &Session::FIRST_PACKET_IDS;
+        // Handshake (765, "a", 25565, status) with an extra byte
+        let mut stream: VecDeque<u8> =
+            VecDeque::from([0x09, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x01, 0xaa]);
+        let header = read_packet_header_from_stream(&mut session, &mut stream).unwrap();
+        assert_eq!((header.length, header.body_length), (9, 8));
+        let error = read_packet_body_from_stream(&mut session, &mut stream, &header).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<PacketError>(),
+            Some(PacketError::TrailingBytes(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_packet_short_body() {
+        let mut session = status_session();
+        // Ping Request declared with 4 bytes of payload instead of 8, followed by a Status Request
+        let mut stream: VecDeque<u8> =
+            VecDeque::from([0x05, 0x01, 0x00, 0x00, 0x00, 0x2a, 0x01, 0x00]);
+        assert!(read_packet(&mut session, &mut stream).is_err());
+        // the next packet is left untouched
+        assert_eq!(stream, [0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_read_packet_invalid_length() {
+        let mut session = status_session();
+        // longer than the Ping Request can be
+        let mut stream: VecDeque<u8> = VecDeque::from([0x7f, 0x01]);
+        let error = read_packet(&mut session, &mut stream).unwrap_err();
+        assert!(error.to_string().contains("too long"));
+        // longer than any packet can be
+        let mut stream: VecDeque<u8> = VecDeque::from([0x80, 0x80, 0x80, 0x01, 0x00]);
+        assert!(read_packet(&mut session, &mut stream).is_err());
+        // the packet id does not fit in the declared length
+        let mut stream: VecDeque<u8> = VecDeque::from([0x01, 0x80, 0x00]);
+        assert!(read_packet(&mut session, &mut stream).is_err());
+    }
+}
@@ -1,97 +1,166 @@
-use super::datatype::varint;
+use super::datatype::{boolean, long, short, varint, Decode};
+use super::registry::{self, EncryptionResponseLayout};
 use super::{
-    s2c_disconnect, ClientBoundPacketBody, PacketBody, PacketError, Result, ServerBoundPacketBody,
+    read_byte_array, s2c_disconnect, ClientBoundPacketBody, PacketBody, ReadPacket, Result,
+    ServerBoundPacketBody,
 };
+use crate::encryption;
 use crate::session::Session;
-use fake_minecraft_server::encryption;
-use std::io::Read;
-use std::net::TcpStream;
+use fake_minecraft_server_derive::Packet;
+use log::{info, warn};
+use std::io::{Read, Write};
 
-#[derive(Debug)]
+#[derive(Debug, Packet)]
+#[packet(
+    id = 0x01,
+    state = Login,
+    direction = Serverbound,
+    max_length = C2SEncryptionResponse::MAX_LENGTH,
+    manual
+)]
+#[allow(dead_code)]
 pub struct C2SEncryptionResponse {
-    pub shared_secret_length: varint::VarInt,
-    pub decrypted_shared_secret: Vec<u8>,
-    pub verify_token_length: varint::VarInt,
-    pub decrypted_verify_token: Vec<u8>,
+    pub decrypted_shared_secret: Option<Vec<u8>>,
+    pub decrypted_verify_token: Option<Vec<u8>>,
     pub is_authenticated: bool,
+    // the reason the client is kicked with when the key exchange failed
+    pub error: Option<String>,
 }
 
 impl C2SEncryptionResponse {
-    pub const PACKET_ID: i32 = 0x01;
-
     const NEXT_PACKET_IDS: [i32; 0] = []; // terminate connection
 
-    fn read_byte_array(stream: &mut impl Read, length: usize) -> Result<Vec<u8>> {
-        let mut array: Vec<u8> = vec![0; length];
-        if let Err(e) = stream.read_exact(&mut array) {
-            return Err(PacketError::ReadError(format!("Could not read byte array: {}", e)).into());
-        }
-        Ok(array)
+    const DECRYPTION_FAILED_REASON: &'static str = "Failed to decrypt the encryption response";
+
+    const INVALID_VERIFY_TOKEN_REASON: &'static str = "Invalid verify token";
+
+    // id, shared secret, [has verify token, salt], verify token or signature
+    // (RSA ciphertexts and signatures are at most 256 bytes)
+    const MAX_LENGTH: i32 = 1 + (3 + 256) + (1 + 8) + (3 + 256);
+
+    fn read_prefixed_byte_array(
+        stream: &mut impl Read,
+        layout: EncryptionResponseLayout,
+    ) -> Result<Vec<u8>> {
+        let length = match layout {
+            EncryptionResponseLayout::ShortPrefixed => short::Short::decode(stream)?.value as i32,
+            _ => varint::VarInt::decode(stream)?.value,
+        };
+        read_byte_array(stream, length)
     }
 }
 
 impl PacketBody for C2SEncryptionResponse {
     fn update_session(&self, session: &mut Session) {
         session.next_packet_ids = &Self::NEXT_PACKET_IDS;
-        session.shared_secret = Some(self.decrypted_shared_secret.clone());
+        // the client encrypts from here on, unless its secret could not be decrypted
+        session.shared_secret = self.decrypted_shared_secret.clone();
         session.is_authenticated = self.is_authenticated;
     }
 }
 
-impl ServerBoundPacketBody for C2SEncryptionResponse {
+impl ReadPacket for C2SEncryptionResponse {
     fn read_from_stream(
         session: &mut Session,
         stream: &mut impl Read,
     ) -> Result<Box<dyn ServerBoundPacketBody>> {
-        let shared_secret_length = varint::read_from_stream(stream)?;
-        let shared_secret: Vec<u8> =
-            Self::read_byte_array(stream, shared_secret_length.value as usize)?;
+        let layout =
+            registry::get_encryption_response_layout(session.protocol_version.unwrap_or_default());
 
-        let verify_token_length = varint::read_from_stream(stream)?;
-        let verify_token: Vec<u8> =
-            Self::read_byte_array(stream, verify_token_length.value as usize)?;
+        let shared_secret = Self::read_prefixed_byte_array(stream, layout)?;
+
+        // 1.19 - 1.19.2 clients with a chat signing key send a signed salt instead of the token
+        let verify_token = match layout {
+            EncryptionResponseLayout::VerifyTokenOrSignature => {
+                if boolean::Boolean::decode(stream)?.value {
+                    Some(Self::read_prefixed_byte_array(stream, layout)?)
+                } else {
+                    long::Long::decode(stream)?;
+                    Self::read_prefixed_byte_array(stream, layout)?;
+                    None
+                }
+            }
+            _ => Some(Self::read_prefixed_byte_array(stream, layout)?),
+        };
+
+        let rsa = session.rsa.as_ref().unwrap();
 
         // decrypt shared secret
-        let mut decrypted_shared_secret = session
-            .rsa
-            .as_ref()
-            .unwrap()
-            .decrypt_bytes(&shared_secret)?;
+        let mut decrypted_shared_secret = match rsa.decrypt_bytes(&shared_secret) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Could not decrypt shared secret: {}", e);
+                return Ok(Box::new(C2SEncryptionResponse {
+                    decrypted_shared_secret: None,
+                    decrypted_verify_token: None,
+                    is_authenticated: false,
+                    error: Some(Self::DECRYPTION_FAILED_REASON.to_string()),
+                }));
+            }
+        };
         decrypted_shared_secret.resize(16, 0);
 
-        // decrypt verify token
-        let mut decrypted_verify_token =
-            session.rsa.as_ref().unwrap().decrypt_bytes(&verify_token)?;
-        decrypted_verify_token.resize(4, 0);
-
-        // check verify token
-        if decrypted_verify_token != *session.verify_token.as_ref().unwrap() {
-            return Err(PacketError::EncryptionError("Invalid verify token".to_string()).into());
+        // decrypt and check verify token
+        let decrypted_verify_token = match verify_token.map(|t| rsa.decrypt_bytes(&t)) {
+            Some(Ok(mut t)) => {
+                t.resize(4, 0);
+                Some(t)
+            }
+            Some(Err(e)) => {
+                warn!("Could not decrypt verify token: {}", e);
+                return Ok(Box::new(C2SEncryptionResponse {
+                    decrypted_shared_secret: Some(decrypted_shared_secret),
+                    decrypted_verify_token: None,
+                    is_authenticated: false,
+                    error: Some(Self::DECRYPTION_FAILED_REASON.to_string()),
+                }));
+            }
+            None => None,
+        };
+        if let Some(t) = &decrypted_verify_token {
+            if t != session.verify_token.as_ref().unwrap() {
+                return Ok(Box::new(C2SEncryptionResponse {
+                    decrypted_shared_secret: Some(decrypted_shared_secret),
+                    decrypted_verify_token,
+                    is_authenticated: false,
+                    error: Some(Self::INVALID_VERIFY_TOKEN_REASON.to_string()),
+                }));
+            }
         }
 
         // authenticate
         let auth_res = encryption::authenticate(
             &decrypted_shared_secret,
-            &session
-                .rsa
-                .as_ref()
-                .unwrap()
-                .get_public_key_in_der()
-                .unwrap(),
-            &session.username.as_ref().unwrap(),
+            &rsa.get_public_key_in_der()?,
+            session.username.as_ref().unwrap(),
         );
+        if let Err(e) = &auth_res {
+            info!(
+                "{} is not authenticated: {}",
+                session.username.as_ref().unwrap(),
+                e
+            );
+        }
 
         Ok(Box::new(C2SEncryptionResponse {
-            shared_secret_length,
-            decrypted_shared_secret,
-            verify_token_length,
+            decrypted_shared_secret: Some(decrypted_shared_secret),
             decrypted_verify_token,
             is_authenticated: auth_res.is_ok(),
+            error: None,
         }))
     }
+}
 
-    fn respond(&self, session: &mut Session, stream: &mut TcpStream) -> Result<()> {
-        let response_packet = s2c_disconnect::S2CDisconnectPacket::new();
+impl ServerBoundPacketBody for C2SEncryptionResponse {
+    fn respond(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()> {
+        // authentication failures are kicked with the unauthenticated template
+        let response_packet = match &self.error {
+            Some(reason) => {
+                warn!("Rejected {}: {}", session.peer_address, reason);
+                s2c_disconnect::S2CDisconnectPacket::with_reason(reason.clone())
+            }
+            None => s2c_disconnect::S2CDisconnectPacket::new(session),
+        };
         response_packet.write_to_stream(session, stream)?;
         response_packet.update_session(session);
         Ok(())
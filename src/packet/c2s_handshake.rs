@@ -1,24 +1,43 @@
-use super::datatype::{string, unsigned_short, varint};
-use super::{PacketBody, PacketError, Result, ServerBoundPacketBody};
+use super::datatype::{string, unsigned_short, varint, Decode};
+use super::{
+    s2c_disconnect, ClientBoundPacketBody, PacketBody, PacketError, ReadPacket, Result,
+    ServerBoundPacketBody,
+};
+use crate::forwarding::{self, ForwardedPlayer, ForwardingError, ForwardingMode};
 use crate::session::{Session, SessionState};
+use fake_minecraft_server_derive::Packet;
+use log::{info, warn};
 use std::fmt::Debug;
-use std::io::Read;
-use std::net::TcpStream;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
 
-#[derive(Debug)]
+#[derive(Debug, Packet)]
+#[packet(
+    id = 0x00,
+    state = Handshaking,
+    direction = Serverbound,
+    max_length = C2SHandshakePacket::MAX_LENGTH,
+    manual
+)]
 pub struct C2SHandshakePacket {
     protocol_version: varint::VarInt,
     server_address: string::String,
     server_port: unsigned_short::UnsignedShort,
     next_state: varint::VarInt,
+    forwarding: Option<std::result::Result<ForwardedPlayer, ForwardingError>>,
 }
 
 impl C2SHandshakePacket {
-    pub const PACKET_ID: i32 = 0x00;
-
     const NEXT_PACKET_IDS_STATUS: [i32; 1] = [0x00]; // Status Request
 
     const NEXT_PACKET_IDS_LOGIN: [i32; 1] = [0x00]; // Login Start
+
+    const NEXT_PACKET_IDS_REJECTED: [i32; 0] = []; // terminate connection
+
+    const SERVER_ADDRESS_MAX_LENGTH: i32 = 255;
+
+    // id, protocol version, server address (long enough for BungeeCord), port, next state
+    const MAX_LENGTH: i32 = 1 + 5 + (3 + 3 * string::String::MAX_LENGTH) + 2 + 1;
 }
 
 impl PacketBody for C2SHandshakePacket {
@@ -37,21 +56,57 @@ impl PacketBody for C2SHandshakePacket {
             }
             _ => unreachable!(),
         }
+
+        match &self.forwarding {
+            Some(Ok(forwarded)) => {
+                session.server_address = Some(forwarded.server_address.clone());
+                session.peer_address =
+                    SocketAddr::new(forwarded.address, session.peer_address.port());
+                session.uuid = Some(forwarded.uuid);
+                session.properties = forwarded.properties.clone();
+                session.locate();
+            }
+            Some(Err(_)) => {
+                session.next_packet_ids = &C2SHandshakePacket::NEXT_PACKET_IDS_REJECTED;
+            }
+            None => {}
+        }
+        session.profile = session
+            .server
+            .virtual_hosts
+            .read()
+            .unwrap()
+            .resolve(session.server_address.as_ref().unwrap());
+
+        if let SessionState::LOGIN = session.state {
+            if session
+                .profile
+                .check_protocol_version(self.protocol_version.value)
+                .is_err()
+            {
+                session.next_packet_ids = &C2SHandshakePacket::NEXT_PACKET_IDS_REJECTED;
+            }
+        }
     }
 }
 
-impl ServerBoundPacketBody for C2SHandshakePacket {
+impl ReadPacket for C2SHandshakePacket {
     fn read_from_stream(
-        _: &mut Session,
+        session: &mut Session,
         stream: &mut impl Read,
     ) -> Result<Box<dyn ServerBoundPacketBody>> {
-        let protocol_version = varint::read_from_stream(stream)?;
+        let protocol_version = varint::VarInt::decode(stream)?;
 
-        let server_address = string::read_from_stream(stream)?;
+        // BungeeCord appends the forwarded player to the server address
+        let max_length = match session.server.forwarding_mode {
+            ForwardingMode::BungeeCord => string::String::MAX_LENGTH,
+            _ => Self::SERVER_ADDRESS_MAX_LENGTH,
+        };
+        let server_address = string::read_with_max_length(stream, max_length)?;
 
-        let server_port = unsigned_short::read_from_stream(stream)?;
+        let server_port = unsigned_short::UnsignedShort::decode(stream)?;
 
-        let next_state = varint::read_from_stream(stream)?;
+        let next_state = varint::VarInt::decode(stream)?;
         if next_state.value != 1 && next_state.value != 2 {
             return Err(PacketError::SequenceError(format!(
                 "Invalid next state for C2SHandshakePacket: {}",
@@ -60,14 +115,48 @@ impl ServerBoundPacketBody for C2SHandshakePacket {
             .into());
         }
 
+        // BungeeCord only forwards players on login
+        let forwarding = if next_state.value == 2
+            && session.server.forwarding_mode == ForwardingMode::BungeeCord
+        {
+            Some(forwarding::parse_bungeecord(&server_address.value))
+        } else {
+            None
+        };
+
         Ok(Box::new(C2SHandshakePacket {
             protocol_version,
             server_address,
             server_port,
             next_state,
+            forwarding,
         }))
     }
-    fn respond(&self, _: &mut Session, _: &mut TcpStream) -> Result<()> {
+}
+
+impl ServerBoundPacketBody for C2SHandshakePacket {
+    fn respond(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()> {
+        if let Some(Err(e)) = &self.forwarding {
+            warn!("Rejected {}: {}", session.peer_address, e);
+            let response_packet = s2c_disconnect::S2CDisconnectPacket::with_reason(e.0.clone());
+            response_packet.write_to_stream(session, stream)?;
+            return Ok(());
+        }
+
+        // unsupported versions are kicked before any encryption
+        if let SessionState::LOGIN = session.state {
+            if let Err(reason) = session
+                .profile
+                .check_protocol_version(self.protocol_version.value)
+            {
+                info!(
+                    "Rejected {}: unsupported protocol version {}",
+                    session.peer_address, self.protocol_version.value
+                );
+                let response_packet = s2c_disconnect::S2CDisconnectPacket::with_reason(reason);
+                response_packet.write_to_stream(session, stream)?;
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,282 @@
+use super::datatype::{boolean, string, uuid, varint, Decode};
+use super::registry;
+use super::{
+    s2c_disconnect, ClientBoundPacketBody, PacketBody, PacketError, ReadPacket, RecordingReader,
+    Result, ServerBoundPacketBody,
+};
+use crate::encryption;
+use crate::forwarding::{ForwardingError, ProfileProperty};
+use crate::session::Session;
+use fake_minecraft_server_derive::Packet;
+use log::{info, warn};
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub struct VelocityPlayerInfo {
+    pub address: IpAddr,
+    pub uuid: u128,
+    pub username: String,
+    pub properties: Vec<ProfileProperty>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(
+    id = 0x02,
+    state = Login,
+    direction = Serverbound,
+    min_protocol = registry::PROTOCOL_1_13,
+    max_length = C2SLoginPluginResponse::MAX_LENGTH,
+    manual
+)]
+#[allow(dead_code)]
+pub struct C2SLoginPluginResponse {
+    pub message_id: varint::VarInt,
+    pub forwarding: std::result::Result<VelocityPlayerInfo, ForwardingError>,
+}
+
+impl C2SLoginPluginResponse {
+    const NEXT_PACKET_IDS: [i32; 0] = []; // terminate connection
+
+    const SIGNATURE_LENGTH: usize = 32;
+    const MAX_PROPERTIES: i32 = 64;
+
+    // id, message id, successful, data (vanilla limits it to 1 MiB)
+    const MAX_LENGTH: i32 = 1 + 5 + 1 + 1048576;
+
+    fn read_player_info(stream: &mut impl Read) -> Result<VelocityPlayerInfo> {
+        let version = varint::VarInt::decode(stream)?;
+        if version.value != 1 {
+            return Err(ForwardingError(format!(
+                "Unsupported Velocity forwarding version: {}",
+                version.value
+            ))
+            .into());
+        }
+
+        let address = string::String::decode(stream)?;
+        let address = match IpAddr::from_str(&address.value) {
+            Ok(a) => a,
+            Err(_) => {
+                return Err(ForwardingError(format!(
+                    "Invalid forwarded address: {}",
+                    address.value
+                ))
+                .into())
+            }
+        };
+        let uuid = uuid::UUID::decode(stream)?;
+        let username = string::String::decode(stream)?;
+
+        let properties_length = varint::VarInt::decode(stream)?;
+        if !(0..=Self::MAX_PROPERTIES).contains(&properties_length.value) {
+            return Err(ForwardingError(format!(
+                "Invalid number of forwarded properties: {}",
+                properties_length.value
+            ))
+            .into());
+        }
+        let mut properties: Vec<ProfileProperty> = vec![];
+        for _ in 0..properties_length.value {
+            let name = string::String::decode(stream)?;
+            let value = string::String::decode(stream)?;
+            let has_signature = boolean::Boolean::decode(stream)?;
+            let signature = if has_signature.value {
+                Some(string::String::decode(stream)?.value)
+            } else {
+                None
+            };
+            properties.push(ProfileProperty {
+                name: name.value,
+                value: value.value,
+                signature,
+            });
+        }
+
+        Ok(VelocityPlayerInfo {
+            address,
+            uuid: uuid.value,
+            username: username.value,
+            properties,
+        })
+    }
+
+    fn verify(
+        session: &Session,
+        stream: &mut impl Read,
+    ) -> std::result::Result<VelocityPlayerInfo, Box<dyn std::error::Error>> {
+        let secret = session.server.velocity_secret()?;
+
+        let mut signature: [u8; Self::SIGNATURE_LENGTH] = [0; Self::SIGNATURE_LENGTH];
+        if let Err(e) = stream.read_exact(&mut signature) {
+            return Err(PacketError::ReadError(format!("Could not read signature: {}", e)).into());
+        }
+
+        // the signature covers every byte that follows it
+        let mut recorder = RecordingReader {
+            inner: stream,
+            bytes: vec![],
+        };
+        let player_info = Self::read_player_info(&mut recorder)?;
+        if !encryption::verify_hmac_sha256(secret.as_bytes(), &recorder.bytes, &signature)? {
+            return Err(ForwardingError("Unable to verify player details".to_string()).into());
+        }
+
+        Ok(player_info)
+    }
+}
+
+impl PacketBody for C2SLoginPluginResponse {
+    fn update_session(&self, session: &mut Session) {
+        session.next_packet_ids = &Self::NEXT_PACKET_IDS;
+        if let Ok(player_info) = &self.forwarding {
+            session.peer_address =
+                SocketAddr::new(player_info.address, session.peer_address.port());
+            session.uuid = Some(player_info.uuid);
+            session.username = Some(player_info.username.clone());
+            session.properties = player_info.properties.clone();
+            session.locate();
+        }
+    }
+}
+
+impl ReadPacket for C2SLoginPluginResponse {
+    fn read_from_stream(
+        session: &mut Session,
+        stream: &mut impl Read,
+    ) -> Result<Box<dyn ServerBoundPacketBody>> {
+        let message_id = varint::VarInt::decode(stream)?;
+        if Some(message_id.value) != session.plugin_message_id {
+            return Err(PacketError::SequenceError(format!(
+                "Unexpected login plugin message id: {}",
+                message_id.value
+            ))
+            .into());
+        }
+
+        let successful = boolean::Boolean::decode(stream)?;
+        let forwarding = if successful.value {
+            match Self::verify(session, stream) {
+                Ok(player_info) => Ok(player_info),
+                Err(e) => match e.downcast::<ForwardingError>() {
+                    Ok(forwarding_error) => Err(*forwarding_error),
+                    Err(e) => return Err(e),
+                },
+            }
+        } else {
+            Err(ForwardingError(
+                "This server requires you to connect with Velocity.".to_string(),
+            ))
+        };
+
+        Ok(Box::new(C2SLoginPluginResponse {
+            message_id,
+            forwarding,
+        }))
+    }
+}
+
+impl ServerBoundPacketBody for C2SLoginPluginResponse {
+    fn respond(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()> {
+        let response_packet = match &self.forwarding {
+            Ok(player_info) => {
+                let ban_reason = session.server.ban_list.read().unwrap().check(
+                    &player_info.address,
+                    &player_info.username,
+                    Some(player_info.uuid),
+                );
+                match ban_reason {
+                    Some(reason) => {
+                        info!(
+                            "Kicked {}: {}",
+                            player_info.username,
+                            reason.replace('\n', " ")
+                        );
+                        s2c_disconnect::S2CDisconnectPacket::with_reason(reason)
+                    }
+                    None => s2c_disconnect::S2CDisconnectPacket::new(session),
+                }
+            }
+            Err(e) => {
+                warn!("Rejected {}: {}", session.peer_address, e);
+                s2c_disconnect::S2CDisconnectPacket::with_reason(e.0.clone())
+            }
+        };
+        response_packet.write_to_stream(session, stream)?;
+        response_packet.update_session(session);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ban_list::{BanList, PlayerBanEntry};
+    use crate::forwarding::ForwardingMode;
+    use crate::packet::datatype::Encode;
+    use crate::server::Server;
+    use std::sync::{Arc, RwLock};
+
+    fn sign(secret: &[u8], data: &[u8]) -> Vec<u8> {
+        let pkey = openssl::pkey::PKey::hmac(secret).unwrap();
+        let mut signer =
+            openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &pkey).unwrap();
+        signer.update(data).unwrap();
+        signer.sign_to_vec().unwrap()
+    }
+
+    fn respond(ban_list: BanList) -> (Session, String) {
+        let mut server = Server {
+            forwarding_mode: ForwardingMode::Velocity,
+            forwarding_secret: Some("secret".to_string()),
+            ..Server::default()
+        };
+        server.ban_list = RwLock::new(ban_list);
+        // connected from Velocity, which is not banned itself
+        let mut session = Session::new("127.0.0.1:12345".parse().unwrap(), Arc::new(server));
+        session.protocol_version = Some(765);
+        session.plugin_message_id = Some(1);
+
+        let mut player_info: Vec<u8> = vec![];
+        varint::VarInt::from(1).encode(&mut player_info);
+        string::String::from("203.0.113.7").encode(&mut player_info);
+        uuid::UUID::from(0x069a79f444e94726a5befca90e38aaf5).encode(&mut player_info);
+        string::String::from("Notch").encode(&mut player_info);
+        varint::VarInt::from(0).encode(&mut player_info);
+        let mut body: Vec<u8> = vec![0x01, 0x01];
+        body.extend(sign(b"secret", &player_info));
+        body.extend(player_info);
+
+        let packet =
+            C2SLoginPluginResponse::read_from_stream(&mut session, &mut &body[..]).unwrap();
+        packet.update_session(&mut session);
+        let mut output: Vec<u8> = vec![];
+        packet.respond(&mut session, &mut output).unwrap();
+        (session, String::from_utf8_lossy(&output).to_string())
+    }
+
+    #[test]
+    fn test_banned_forwarded_player() {
+        let ban_list = BanList::new(
+            serde_json::from_str(r#"[{"ip": "203.0.113.0/24", "reason": "Spam"}]"#).unwrap(),
+            vec![],
+            None,
+        );
+        let (session, output) = respond(ban_list);
+        assert_eq!(session.peer_address.to_string(), "203.0.113.7:12345");
+        assert!(output.contains("Your IP address is banned from this server.\\nReason: Spam"));
+
+        // banned by the forwarded UUID, whatever name the proxy announced at Login Start
+        let players: Vec<PlayerBanEntry> = serde_json::from_str(
+            r#"[{"uuid": "069a79f4-44e9-4726-a5be-fca90e38aaf5", "reason": "Griefing"}]"#,
+        )
+        .unwrap();
+        let (_, output) = respond(BanList::new(vec![], players, None));
+        assert!(output.contains("You are banned from this server.\\nReason: Griefing"));
+
+        // the default disconnect message otherwise
+        let (_, output) = respond(BanList::default());
+        assert!(!output.contains("Reason: Spam") && !output.contains("Reason: Griefing"));
+    }
+}
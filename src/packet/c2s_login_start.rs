@@ -1,48 +1,170 @@
-use super::datatype::{string, uuid};
+use super::datatype::{boolean, long, string, uuid, varint, Decode};
+use super::registry::{self, LoginStartLayout};
 use super::{
-    s2c_encryption_request, ClientBoundPacketBody, PacketBody, Result, ServerBoundPacketBody,
+    read_byte_array, s2c_disconnect, s2c_encryption_request, s2c_login_plugin_request,
+    ClientBoundPacketBody, PacketBody, ReadPacket, RecordingReader, Result, ServerBoundPacketBody,
 };
+use crate::forwarding::ForwardingMode;
 use crate::session::Session;
-use std::io::Read;
-use std::net::TcpStream;
+use fake_minecraft_server_derive::Packet;
+use log::info;
+use std::io::{Read, Write};
 
-#[derive(Debug)]
+#[derive(Debug, Packet)]
+#[packet(
+    id = 0x00,
+    state = Login,
+    direction = Serverbound,
+    max_length = C2SLoginStartPacket::MAX_LENGTH,
+    manual
+)]
 pub struct C2SLoginStartPacket {
     pub name: string::String,
-    pub uuid: uuid::UUID,
+    pub uuid: Option<uuid::UUID>,
+    // the body as received, re-sent as is in passthrough mode
+    pub body: Vec<u8>,
 }
 
 impl C2SLoginStartPacket {
-    pub const PACKET_ID: i32 = 0x00;
-
     const NEXT_PACKET_IDS: [i32; 1] = [0x01]; // Encryption Response
+
+    const NEXT_PACKET_IDS_OFFLINE: [i32; 0] = []; // terminate connection (kicked)
+
+    const NEXT_PACKET_IDS_VELOCITY: [i32; 1] = [0x02]; // Login Plugin Response
+
+    const NEXT_PACKET_IDS_PASSTHROUGH: [i32; 0] = []; // terminate connection (spliced to the backend)
+
+    const NAME_MAX_LENGTH: i32 = 16;
+
+    // id, name, [signature data (public key up to 512 bytes, signature up to 4096 bytes)], UUID
+    const MAX_LENGTH: i32 =
+        1 + (1 + 3 * Self::NAME_MAX_LENGTH) + (1 + 8 + (2 + 512) + (2 + 4096)) + (1 + 16);
+
+    const VELOCITY_UNSUPPORTED_REASON: &'static str =
+        "This server requires you to connect with Velocity using Minecraft 1.13 or newer.";
 }
 
 impl PacketBody for C2SLoginStartPacket {
     fn update_session(&self, session: &mut Session) {
         session.username = Some(self.name.value.clone());
-        session.uuid = Some(self.uuid.value.clone());
+        // keep the UUID forwarded by the proxy
+        if session.uuid.is_none() {
+            session.uuid = self.uuid.as_ref().map(|u| u.value);
+        }
         session.next_packet_ids = &Self::NEXT_PACKET_IDS;
     }
 }
 
-impl ServerBoundPacketBody for C2SLoginStartPacket {
+impl ReadPacket for C2SLoginStartPacket {
     fn read_from_stream(
-        _: &mut Session,
+        session: &mut Session,
         stream: &mut impl Read,
     ) -> Result<Box<dyn ServerBoundPacketBody>> {
-        let name = string::read_from_stream(stream)?;
+        let layout = registry::get_login_start_layout(session.protocol_version.unwrap_or_default());
+
+        let mut recorder = RecordingReader {
+            inner: stream,
+            bytes: vec![],
+        };
+        let stream = &mut recorder;
+
+        let name = string::read_with_max_length(stream, Self::NAME_MAX_LENGTH)?;
 
-        let uuid = uuid::read_from_stream(stream)?;
+        if let LoginStartLayout::SignatureData | LoginStartLayout::SignatureDataAndOptionalUuid =
+            layout
+        {
+            // chat signing key is not used
+            if boolean::Boolean::decode(stream)?.value {
+                long::Long::decode(stream)?;
+                let public_key_length = varint::VarInt::decode(stream)?;
+                read_byte_array(stream, public_key_length.value)?;
+                let signature_length = varint::VarInt::decode(stream)?;
+                read_byte_array(stream, signature_length.value)?;
+            }
+        }
 
-        Ok(Box::new(C2SLoginStartPacket { name, uuid }))
+        let uuid = match layout {
+            LoginStartLayout::NameOnly | LoginStartLayout::SignatureData => None,
+            LoginStartLayout::SignatureDataAndOptionalUuid | LoginStartLayout::OptionalUuid => {
+                if boolean::Boolean::decode(stream)?.value {
+                    Some(uuid::UUID::decode(stream)?)
+                } else {
+                    None
+                }
+            }
+            LoginStartLayout::Uuid => Some(uuid::UUID::decode(stream)?),
+        };
+
+        Ok(Box::new(C2SLoginStartPacket {
+            name,
+            uuid,
+            body: recorder.bytes,
+        }))
     }
+}
+
+impl ServerBoundPacketBody for C2SLoginStartPacket {
+    fn respond(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()> {
+        let forwarding_mode = session.server.forwarding_mode;
+
+        // unforwarded addresses are checked on accept, and the one forwarded by Velocity
+        // only after the plugin response
+        let ban_list = session.server.ban_list.read().unwrap();
+        let ban_reason = match forwarding_mode {
+            ForwardingMode::BungeeCord => {
+                ban_list.check(&session.peer_address.ip(), &self.name.value, session.uuid)
+            }
+            _ => ban_list.check_player(&self.name.value, session.uuid),
+        };
+        drop(ban_list);
+        if let Some(reason) = ban_reason {
+            info!("Kicked {}: {}", self.name.value, reason.replace('\n', " "));
+            let response_packet = s2c_disconnect::S2CDisconnectPacket::with_reason(reason);
+            response_packet.write_to_stream(session, stream)?;
+            session.next_packet_ids = &Self::NEXT_PACKET_IDS_OFFLINE;
+            return Ok(());
+        }
+
+        if session.server.passthrough_backend.is_some() {
+            info!("Passing {} through to the backend", self.name.value);
+            session.passthrough_login_start = Some(self.body.clone());
+            session.next_packet_ids = &Self::NEXT_PACKET_IDS_PASSTHROUGH;
+            return Ok(());
+        }
+
+        // login plugin messages were added in 1.13
+        if forwarding_mode == ForwardingMode::Velocity
+            && session.protocol_version.unwrap_or_default() < registry::PROTOCOL_1_13
+        {
+            let response_packet = s2c_disconnect::S2CDisconnectPacket::with_reason(
+                Self::VELOCITY_UNSUPPORTED_REASON.into(),
+            );
+            response_packet.write_to_stream(session, stream)?;
+            session.next_packet_ids = &Self::NEXT_PACKET_IDS_OFFLINE;
+            return Ok(());
+        }
+
+        if forwarding_mode == ForwardingMode::Velocity {
+            let request_packet =
+                s2c_login_plugin_request::S2CLoginPluginRequest::velocity_player_info()?;
+            request_packet.write_to_stream(session, stream)?;
+            request_packet.update_session(session);
+            session.next_packet_ids = &Self::NEXT_PACKET_IDS_VELOCITY;
+            return Ok(());
+        }
+
+        // offline mode or behind BungeeCord: kick without encryption
+        if !session.profile.online_mode || forwarding_mode == ForwardingMode::BungeeCord {
+            let response_packet = s2c_disconnect::S2CDisconnectPacket::new(session);
+            response_packet.write_to_stream(session, stream)?;
+            session.next_packet_ids = &Self::NEXT_PACKET_IDS_OFFLINE;
+            return Ok(());
+        }
 
-    fn respond(&self, session: &mut Session, stream: &mut TcpStream) -> Result<()> {
         let response_packet = s2c_encryption_request::S2CEncryptionRequest::new()?;
 
         response_packet.update_session(session);
 
-        response_packet.write_to_stream(session, stream).into()
+        response_packet.write_to_stream(session, stream)
     }
 }
@@ -1,38 +1,29 @@
 use super::datatype::long;
 use super::{s2c_ping_response, ClientBoundPacketBody, PacketBody, Result, ServerBoundPacketBody};
 use crate::session::Session;
-use std::io::Read;
-use std::net::TcpStream;
+use fake_minecraft_server_derive::Packet;
+use std::io::Write;
 
-#[derive(Debug)]
+#[derive(Debug, Packet)]
+#[packet(id = 0x01, state = Status, direction = Serverbound, max_length = 1 + 8)]
 pub struct C2SPingRequestPacket {
     pub payload: long::Long,
 }
 
 impl C2SPingRequestPacket {
-    pub const PACKET_ID: i32 = 0x01;
-
     const NEXT_PACKET_IDS: [i32; 0] = []; // terminate connection
 }
 
 impl PacketBody for C2SPingRequestPacket {
     fn update_session(&self, session: &mut Session) {
         session.next_packet_ids = &C2SPingRequestPacket::NEXT_PACKET_IDS;
+        session.pinged = true;
     }
 }
 
 impl ServerBoundPacketBody for C2SPingRequestPacket {
-    fn read_from_stream(
-        _: &mut Session,
-        stream: &mut impl Read,
-    ) -> Result<Box<dyn ServerBoundPacketBody>> {
-        let value = long::read_from_stream(stream)?;
-
-        Ok(Box::new(C2SPingRequestPacket { payload: value }))
-    }
-
-    fn respond(&self, session: &mut Session, stream: &mut TcpStream) -> Result<()> {
+    fn respond(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()> {
         let response_packet = s2c_ping_response::S2CPingResponsePacket::new(self.payload.clone());
-        response_packet.write_to_stream(session, stream).into()
+        response_packet.write_to_stream(session, stream)
     }
 }
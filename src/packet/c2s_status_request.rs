@@ -2,34 +2,27 @@ use crate::packet::{
     s2c_status_response, ClientBoundPacketBody, PacketBody, Result, ServerBoundPacketBody,
 };
 use crate::session::Session;
-use std::io::Read;
-use std::net::TcpStream;
+use fake_minecraft_server_derive::Packet;
+use std::io::Write;
 
-#[derive(Debug)]
+#[derive(Debug, Packet)]
+#[packet(id = 0x00, state = Status, direction = Serverbound, max_length = 1)]
 pub struct C2SStatusRequestPacket {}
 
 impl C2SStatusRequestPacket {
-    pub const PACKET_ID: i32 = 0x00;
-
     const NEXT_PACKET_IDS: [i32; 1] = [0x01]; // Status Ping
 }
 
 impl PacketBody for C2SStatusRequestPacket {
     fn update_session(&self, session: &mut Session) {
         session.next_packet_ids = &C2SStatusRequestPacket::NEXT_PACKET_IDS;
+        session.status_requested = true;
     }
 }
 
 impl ServerBoundPacketBody for C2SStatusRequestPacket {
-    fn read_from_stream(
-        _: &mut Session,
-        _: &mut impl Read,
-    ) -> Result<Box<dyn ServerBoundPacketBody>> {
-        Ok(Box::new(C2SStatusRequestPacket {}))
-    }
-
-    fn respond(&self, session: &mut Session, stream: &mut TcpStream) -> Result<()> {
-        let response_packet = s2c_status_response::S2CStatusResponsePacket::new();
-        response_packet.write_to_stream(session, stream).into()
+    fn respond(&self, session: &mut Session, stream: &mut dyn Write) -> Result<()> {
+        let response_packet = s2c_status_response::S2CStatusResponsePacket::new(session);
+        response_packet.write_to_stream(session, stream)
     }
 }
@@ -1,16 +1,51 @@
+use std::io::Read;
 use std::{error, fmt};
 
+pub mod angle;
+pub mod bit_set;
+pub mod boolean;
+pub mod byte;
+pub mod byte_array;
+pub mod double;
+pub mod float;
+pub mod identifier;
+pub mod int;
 pub mod long;
+pub mod nbt;
+pub mod optional;
+pub mod position;
+pub mod prefixed_array;
+pub mod short;
 pub mod string;
+pub mod unsigned_byte;
 pub mod unsigned_short;
 pub mod uuid;
 pub mod varint;
+pub mod varlong;
+
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+pub trait Decode: Sized {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError>;
+}
+
+// raw bytes whose length is either fixed or written as a separate field
+impl Encode for Vec<u8> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
 
 #[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum DatatypeError {
     ReadError,
     ConvertError,
     TooLongStringError,
+    VarIntTooBig,
+    NegativeLength,
 }
 
 impl fmt::Display for DatatypeError {
@@ -19,6 +54,8 @@ impl fmt::Display for DatatypeError {
             DatatypeError::ReadError => write!(f, "Could not read bytes from stream."),
             DatatypeError::ConvertError => write!(f, "Could not convert bytes."),
             DatatypeError::TooLongStringError => write!(f, "String is too long."),
+            DatatypeError::VarIntTooBig => write!(f, "VarInt or VarLong is too big."),
+            DatatypeError::NegativeLength => write!(f, "Length is negative."),
         }
     }
 }
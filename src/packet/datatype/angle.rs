@@ -0,0 +1,76 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+// rotation in steps of 1/256 of a full turn
+#[derive(Debug, PartialEq, Clone)]
+pub struct Angle {
+    pub value: u8,
+}
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Angle {
+        Angle {
+            value: (degrees.rem_euclid(360.0) * 256.0 / 360.0) as u8,
+        }
+    }
+
+    pub fn to_degrees(&self) -> f32 {
+        self.value as f32 * 360.0 / 256.0
+    }
+}
+
+impl From<u8> for Angle {
+    fn from(v: u8) -> Self {
+        Angle { value: v }
+    }
+}
+
+impl Encode for Angle {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(self.value);
+    }
+}
+
+impl Decode for Angle {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Angle> for Vec<u8> {
+    fn from(v: Angle) -> Self {
+        vec![v.value]
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Angle, DatatypeError> {
+    let mut bytes: [u8; 1] = [0; 1];
+    if stream.read_exact(&mut bytes[..]).is_ok() {
+        Ok(Angle::from(bytes[0]))
+    } else {
+        Err(DatatypeError::ReadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_degrees() {
+        assert_eq!(Angle::from_degrees(90.0), Angle::from(64));
+        assert_eq!(Angle::from_degrees(-90.0), Angle::from(192));
+        assert_eq!(Angle::from_degrees(360.0), Angle::from(0));
+        assert_eq!(Angle::from(128).to_degrees(), 180.0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [0, 64, 255] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(Angle::from(v)));
+            assert_eq!(read_from_stream(&mut bytes), Ok(Angle::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use super::{long, prefixed_array, DatatypeError, Decode, Encode};
+use std::io::Read;
+
+// a prefixed array of Longs, bit i is (value[i / 64] >> (i % 64)) & 1
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BitSet {
+    pub value: Vec<i64>,
+}
+
+impl BitSet {
+    pub fn new() -> BitSet {
+        BitSet { value: vec![] }
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        match self.value.get(index / 64) {
+            Some(word) => (word >> (index % 64)) & 1 == 1,
+            None => false,
+        }
+    }
+
+    pub fn set(&mut self, index: usize, bit: bool) {
+        if self.value.len() <= index / 64 {
+            if !bit {
+                return;
+            }
+            self.value.resize(index / 64 + 1, 0);
+        }
+        if bit {
+            self.value[index / 64] |= 1 << (index % 64);
+        } else {
+            self.value[index / 64] &= !(1 << (index % 64));
+        }
+    }
+}
+
+impl From<Vec<i64>> for BitSet {
+    fn from(v: Vec<i64>) -> Self {
+        BitSet { value: v }
+    }
+}
+
+impl Encode for BitSet {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let longs: Vec<long::Long> = self.value.iter().map(|&v| long::Long::from(v)).collect();
+        prefixed_array::PrefixedArray::from(longs).encode(buf);
+    }
+}
+
+impl Decode for BitSet {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<BitSet> for Vec<u8> {
+    fn from(v: BitSet) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<BitSet, DatatypeError> {
+    let longs = prefixed_array::read_from_stream::<long::Long>(stream)?;
+    Ok(BitSet::from(
+        longs
+            .value
+            .into_iter()
+            .map(|l| l.value)
+            .collect::<Vec<i64>>(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_get_set() {
+        let mut bit_set = BitSet::new();
+        bit_set.set(0, true);
+        bit_set.set(65, true);
+        assert!(bit_set.get(0));
+        assert!(!bit_set.get(1));
+        assert!(bit_set.get(65));
+        assert!(!bit_set.get(1000));
+        assert_eq!(bit_set.value, [1, 2]);
+        bit_set.set(65, false);
+        assert_eq!(bit_set.value, [1, 0]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut bit_set = BitSet::new();
+        bit_set.set(3, true);
+        bit_set.set(127, true);
+        let bytes: Vec<u8> = bit_set.clone().into();
+        assert_eq!(bytes.len(), 1 + 2 * 8);
+        let mut bytes: VecDeque<u8> = VecDeque::from(bytes);
+        assert_eq!(read_from_stream(&mut bytes), Ok(bit_set));
+    }
+}
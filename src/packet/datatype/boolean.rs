@@ -0,0 +1,62 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Boolean {
+    pub value: bool,
+}
+
+impl From<bool> for Boolean {
+    fn from(v: bool) -> Self {
+        Boolean { value: v }
+    }
+}
+
+impl Encode for Boolean {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(self.value as u8);
+    }
+}
+
+impl Decode for Boolean {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Boolean> for Vec<u8> {
+    fn from(v: Boolean) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Boolean, DatatypeError> {
+    let mut bytes: [u8; 1] = [0; 1];
+    if stream.read_exact(&mut bytes[..]).is_err() {
+        return Err(DatatypeError::ReadError);
+    }
+    match bytes[0] {
+        0x00 => Ok(Boolean::from(false)),
+        0x01 => Ok(Boolean::from(true)),
+        _ => Err(DatatypeError::ConvertError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_read_from_stream() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x01, 0x00, 0x02]);
+        assert_eq!(read_from_stream(&mut bytes), Ok(Boolean::from(true)));
+        assert_eq!(read_from_stream(&mut bytes), Ok(Boolean::from(false)));
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Err(DatatypeError::ConvertError)
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Byte {
+    pub value: i8,
+}
+
+impl From<i8> for Byte {
+    fn from(v: i8) -> Self {
+        Byte { value: v }
+    }
+}
+
+impl Encode for Byte {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for Byte {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Byte> for Vec<u8> {
+    fn from(v: Byte) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Byte, DatatypeError> {
+    let mut bytes: [u8; 1] = [0; 1];
+    if stream.read_exact(&mut bytes[..]).is_ok() {
+        Ok(Byte::from(i8::from_be_bytes(bytes)))
+    } else {
+        Err(DatatypeError::ReadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let bytes: Vec<u8> = Byte::from(-2).into();
+        assert_eq!(bytes, [0xfe]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [i8::MIN, -1, 0, 1, i8::MAX] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(Byte::from(v)));
+            assert_eq!(read_from_stream(&mut bytes), Ok(Byte::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+}
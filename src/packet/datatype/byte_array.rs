@@ -0,0 +1,74 @@
+use super::{varint, DatatypeError, Decode, Encode};
+use std::io::Read;
+
+// prefixed with its length as a VarInt
+#[derive(Debug, PartialEq, Clone)]
+pub struct ByteArray {
+    pub value: Vec<u8>,
+}
+
+impl From<Vec<u8>> for ByteArray {
+    fn from(v: Vec<u8>) -> Self {
+        ByteArray { value: v }
+    }
+}
+
+impl Encode for ByteArray {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        varint::VarInt::from(self.value.len() as i32).encode(buf);
+        buf.extend_from_slice(&self.value);
+    }
+}
+
+impl Decode for ByteArray {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<ByteArray> for Vec<u8> {
+    fn from(v: ByteArray) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<ByteArray, DatatypeError> {
+    let length = varint::read_from_stream(stream)?;
+    if length.value < 0 {
+        return Err(DatatypeError::NegativeLength);
+    }
+    read_with_length(stream, length.value as usize)
+}
+
+// for byte arrays whose length is known from the context
+pub fn read_with_length(stream: &mut impl Read, length: usize) -> Result<ByteArray, DatatypeError> {
+    let mut bytes: Vec<u8> = Vec::new();
+    match stream.take(length as u64).read_to_end(&mut bytes) {
+        Ok(n) if n == length => Ok(ByteArray::from(bytes)),
+        _ => Err(DatatypeError::ReadError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_round_trip() {
+        for v in [vec![], vec![0x00, 0xff], vec![0x42; 300]] {
+            let bytes: Vec<u8> = ByteArray::from(v.clone()).into();
+            let mut bytes: VecDeque<u8> = VecDeque::from(bytes);
+            assert_eq!(read_from_stream(&mut bytes), Ok(ByteArray::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_read_from_stream_short() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x03, 0x01, 0x02]);
+        assert_eq!(read_from_stream(&mut bytes), Err(DatatypeError::ReadError));
+    }
+}
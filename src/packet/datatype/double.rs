@@ -0,0 +1,63 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Double {
+    pub value: f64,
+}
+
+impl From<f64> for Double {
+    fn from(v: f64) -> Self {
+        Double { value: v }
+    }
+}
+
+impl Encode for Double {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for Double {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Double> for Vec<u8> {
+    fn from(v: Double) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Double, DatatypeError> {
+    let mut bytes: [u8; 8] = [0; 8];
+    if stream.read_exact(&mut bytes[..]).is_ok() {
+        Ok(Double::from(f64::from_be_bytes(bytes)))
+    } else {
+        Err(DatatypeError::ReadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let bytes: Vec<u8> = Double::from(-2.0).into();
+        assert_eq!(bytes, [0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [f64::MIN, -0.5, 0.0, 1.5, f64::MAX, f64::NEG_INFINITY] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(Double::from(v)));
+            assert_eq!(read_from_stream(&mut bytes), Ok(Double::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Float {
+    pub value: f32,
+}
+
+impl From<f32> for Float {
+    fn from(v: f32) -> Self {
+        Float { value: v }
+    }
+}
+
+impl Encode for Float {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for Float {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Float> for Vec<u8> {
+    fn from(v: Float) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Float, DatatypeError> {
+    let mut bytes: [u8; 4] = [0; 4];
+    if stream.read_exact(&mut bytes[..]).is_ok() {
+        Ok(Float::from(f32::from_be_bytes(bytes)))
+    } else {
+        Err(DatatypeError::ReadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let bytes: Vec<u8> = Float::from(1.5).into();
+        assert_eq!(bytes, [0x3f, 0xc0, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [f32::MIN, -0.5, 0.0, 1.5, f32::MAX, f32::INFINITY] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(Float::from(v)));
+            assert_eq!(read_from_stream(&mut bytes), Ok(Float::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use super::{string, DatatypeError, Decode, Encode};
+use std::fmt;
+use std::io::Read;
+
+// namespaced location such as "minecraft:overworld"
+#[derive(Debug, PartialEq, Clone)]
+pub struct Identifier {
+    pub namespace: std::string::String,
+    pub path: std::string::String,
+}
+
+impl Identifier {
+    pub const DEFAULT_NAMESPACE: &'static str = "minecraft";
+
+    pub fn parse(s: &str) -> Result<Identifier, DatatypeError> {
+        let (namespace, path) = match s.split_once(':') {
+            Some((namespace, path)) => (namespace, path),
+            None => (Self::DEFAULT_NAMESPACE, s),
+        };
+        let valid_namespace = namespace
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '.' | '-' | '_'));
+        let valid_path = path
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '.' | '-' | '_' | '/'));
+        if namespace.is_empty() || path.is_empty() || !valid_namespace || !valid_path {
+            return Err(DatatypeError::ConvertError);
+        }
+        Ok(Identifier {
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+
+impl Encode for Identifier {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        string::String::from(self.to_string()).encode(buf);
+    }
+}
+
+impl Decode for Identifier {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Identifier> for Vec<u8> {
+    fn from(v: Identifier) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Identifier, DatatypeError> {
+    Identifier::parse(&string::read_from_stream(stream)?.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Identifier::parse("velocity:player_info")
+                .unwrap()
+                .to_string(),
+            "velocity:player_info"
+        );
+        assert_eq!(
+            Identifier::parse("textures/block/stone.png")
+                .unwrap()
+                .to_string(),
+            "minecraft:textures/block/stone.png"
+        );
+        assert!(Identifier::parse("Minecraft:stone").is_err());
+        assert!(Identifier::parse("minecraft:").is_err());
+        assert!(Identifier::parse("a/b:c").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let identifier = Identifier::parse("minecraft:brand").unwrap();
+        let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(identifier.clone()));
+        assert_eq!(read_from_stream(&mut bytes), Ok(identifier));
+        assert!(bytes.is_empty());
+    }
+}
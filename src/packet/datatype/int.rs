@@ -0,0 +1,63 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Int {
+    pub value: i32,
+}
+
+impl From<i32> for Int {
+    fn from(v: i32) -> Self {
+        Int { value: v }
+    }
+}
+
+impl Encode for Int {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for Int {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Int> for Vec<u8> {
+    fn from(v: Int) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Int, DatatypeError> {
+    let mut bytes: [u8; 4] = [0; 4];
+    if stream.read_exact(&mut bytes[..]).is_ok() {
+        Ok(Int::from(i32::from_be_bytes(bytes)))
+    } else {
+        Err(DatatypeError::ReadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let bytes: Vec<u8> = Int::from(-2).into();
+        assert_eq!(bytes, [0xff, 0xff, 0xff, 0xfe]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [i32::MIN, -1, 0, 25565, i32::MAX] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(Int::from(v)));
+            assert_eq!(read_from_stream(&mut bytes), Ok(Int::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+}
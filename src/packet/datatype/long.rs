@@ -1,4 +1,4 @@
-use super::DatatypeError;
+use super::{DatatypeError, Decode, Encode};
 use std::io::Read;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -20,15 +20,29 @@ impl From<i64> for Long {
     }
 }
 
-impl Into<Vec<u8>> for Long {
-    fn into(self) -> Vec<u8> {
-        self.value.to_be_bytes().to_vec()
+impl Encode for Long {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for Long {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Long> for Vec<u8> {
+    fn from(v: Long) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
     }
 }
 
 pub fn read_from_stream(stream: &mut impl Read) -> Result<Long, DatatypeError> {
     let mut bytes: [u8; 8] = [0; 8];
-    if let Ok(_) = stream.read_exact(&mut bytes[..]) {
+    if stream.read_exact(&mut bytes[..]).is_ok() {
         Ok(Long::from(&bytes))
     } else {
         Err(DatatypeError::ReadError)
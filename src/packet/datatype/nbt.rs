@@ -0,0 +1,682 @@
+use super::{DatatypeError, Decode, Encode};
+use serde_json::{Map, Number, Value};
+use std::io::Read;
+
+// https://wiki.vg/NBT
+#[derive(Debug, PartialEq, Clone)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    // all elements must have the same type, an empty list is written with TAG_End
+    List(Vec<Tag>),
+    // keeps the order of the entries so that files are written back byte for byte
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    const END: u8 = 0;
+    const COMPOUND: u8 = 10;
+    // same limit as vanilla
+    const MAX_DEPTH: usize = 512;
+
+    pub fn type_id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.iter().find(|(n, _)| n == name).map(|(_, t)| t),
+            _ => None,
+        }
+    }
+
+    // lengths and element types that cannot be written
+    pub fn validate(&self) -> Result<(), DatatypeError> {
+        match self {
+            Tag::String(v) => check_string(v),
+            Tag::List(v) => {
+                if v.windows(2).any(|w| w[0].type_id() != w[1].type_id()) {
+                    return Err(DatatypeError::ConvertError);
+                }
+                v.iter().try_for_each(Tag::validate)
+            }
+            Tag::Compound(v) => v.iter().try_for_each(|(name, tag)| {
+                check_string(name)?;
+                tag.validate()
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // must be validated first
+    fn write_payload(&self, buf: &mut Vec<u8>) {
+        match self {
+            Tag::Byte(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            Tag::Short(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            Tag::Int(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            Tag::Long(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            Tag::Float(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            Tag::Double(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            Tag::ByteArray(v) => {
+                buf.extend_from_slice(&(v.len() as i32).to_be_bytes());
+                buf.extend(v.iter().map(|&b| b as u8));
+            }
+            Tag::String(v) => write_string(v, buf),
+            Tag::List(v) => {
+                buf.push(v.first().map_or(Self::END, |t| t.type_id()));
+                buf.extend_from_slice(&(v.len() as i32).to_be_bytes());
+                for tag in v {
+                    tag.write_payload(buf);
+                }
+            }
+            Tag::Compound(v) => {
+                for (name, tag) in v {
+                    buf.push(tag.type_id());
+                    write_string(name, buf);
+                    tag.write_payload(buf);
+                }
+                buf.push(Self::END);
+            }
+            Tag::IntArray(v) => {
+                buf.extend_from_slice(&(v.len() as i32).to_be_bytes());
+                for i in v {
+                    buf.extend_from_slice(&i.to_be_bytes());
+                }
+            }
+            Tag::LongArray(v) => {
+                buf.extend_from_slice(&(v.len() as i32).to_be_bytes());
+                for l in v {
+                    buf.extend_from_slice(&l.to_be_bytes());
+                }
+            }
+        }
+    }
+
+    fn read_payload(
+        type_id: u8,
+        stream: &mut impl Read,
+        depth: usize,
+    ) -> Result<Tag, DatatypeError> {
+        if depth > Self::MAX_DEPTH {
+            return Err(DatatypeError::ConvertError);
+        }
+        Ok(match type_id {
+            1 => Tag::Byte(i8::from_be_bytes(read_array(stream)?)),
+            2 => Tag::Short(i16::from_be_bytes(read_array(stream)?)),
+            3 => Tag::Int(i32::from_be_bytes(read_array(stream)?)),
+            4 => Tag::Long(i64::from_be_bytes(read_array(stream)?)),
+            5 => Tag::Float(f32::from_be_bytes(read_array(stream)?)),
+            6 => Tag::Double(f64::from_be_bytes(read_array(stream)?)),
+            7 => {
+                let length = read_length(stream)?;
+                let mut bytes: Vec<u8> = Vec::new();
+                if stream.take(length as u64).read_to_end(&mut bytes).ok() != Some(length) {
+                    return Err(DatatypeError::ReadError);
+                }
+                Tag::ByteArray(bytes.into_iter().map(|b| b as i8).collect())
+            }
+            8 => Tag::String(read_string(stream)?),
+            9 => {
+                let element_type = read_array::<1>(stream)?[0];
+                let length = read_length(stream)?;
+                if element_type == Self::END && length > 0 {
+                    return Err(DatatypeError::ConvertError);
+                }
+                let mut tags: Vec<Tag> = Vec::new();
+                for _ in 0..length {
+                    tags.push(Self::read_payload(element_type, stream, depth + 1)?);
+                }
+                Tag::List(tags)
+            }
+            10 => {
+                let mut entries: Vec<(String, Tag)> = Vec::new();
+                loop {
+                    let entry_type = read_array::<1>(stream)?[0];
+                    if entry_type == Self::END {
+                        break;
+                    }
+                    let name = read_string(stream)?;
+                    entries.push((name, Self::read_payload(entry_type, stream, depth + 1)?));
+                }
+                Tag::Compound(entries)
+            }
+            11 => {
+                let length = read_length(stream)?;
+                let mut ints: Vec<i32> = Vec::new();
+                for _ in 0..length {
+                    ints.push(i32::from_be_bytes(read_array(stream)?));
+                }
+                Tag::IntArray(ints)
+            }
+            12 => {
+                let length = read_length(stream)?;
+                let mut longs: Vec<i64> = Vec::new();
+                for _ in 0..length {
+                    longs.push(i64::from_be_bytes(read_array(stream)?));
+                }
+                Tag::LongArray(longs)
+            }
+            _ => return Err(DatatypeError::ConvertError),
+        })
+    }
+}
+
+impl From<&Tag> for Value {
+    fn from(v: &Tag) -> Self {
+        match v {
+            Tag::Byte(b) => Value::from(*b),
+            Tag::Short(s) => Value::from(*s),
+            Tag::Int(i) => Value::from(*i),
+            Tag::Long(l) => Value::from(*l),
+            // NaN and infinities become null
+            Tag::Float(f) => Value::from(*f),
+            Tag::Double(d) => Value::from(*d),
+            Tag::ByteArray(a) => Value::from(a.clone()),
+            Tag::String(s) => Value::from(s.clone()),
+            Tag::List(l) => Value::Array(l.iter().map(Value::from).collect()),
+            Tag::Compound(c) => Value::Object(
+                c.iter()
+                    .map(|(name, tag)| (name.clone(), Value::from(tag)))
+                    .collect::<Map<String, Value>>(),
+            ),
+            Tag::IntArray(a) => Value::from(a.clone()),
+            Tag::LongArray(a) => Value::from(a.clone()),
+        }
+    }
+}
+
+// booleans become bytes, integers ints or longs, and other numbers doubles
+impl TryFrom<&Value> for Tag {
+    type Error = DatatypeError;
+
+    fn try_from(v: &Value) -> Result<Self, Self::Error> {
+        Ok(match v {
+            Value::Null => return Err(DatatypeError::ConvertError),
+            Value::Bool(b) => Tag::Byte(*b as i8),
+            Value::Number(n) => number_to_tag(n)?,
+            Value::String(s) => Tag::String(s.clone()),
+            Value::Array(a) => {
+                let tags = a.iter().map(Tag::try_from).collect::<Result<Vec<_>, _>>()?;
+                // all elements of a list must have the same type
+                if tags.windows(2).any(|w| w[0].type_id() != w[1].type_id()) {
+                    return Err(DatatypeError::ConvertError);
+                }
+                Tag::List(tags)
+            }
+            Value::Object(o) => Tag::Compound(
+                o.iter()
+                    .map(|(name, value)| Ok((name.clone(), Tag::try_from(value)?)))
+                    .collect::<Result<Vec<_>, DatatypeError>>()?,
+            ),
+        })
+    }
+}
+
+fn number_to_tag(n: &Number) -> Result<Tag, DatatypeError> {
+    if let Some(i) = n.as_i64() {
+        return Ok(match i32::try_from(i) {
+            Ok(i) => Tag::Int(i),
+            Err(_) => Tag::Long(i),
+        });
+    }
+    match n.as_f64() {
+        Some(f) => Ok(Tag::Double(f)),
+        None => Err(DatatypeError::ConvertError),
+    }
+}
+
+// network NBT (1.20.2+): the root tag has no name and may be of any type
+// the default is TAG_End, sent for an absent value
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Nbt {
+    // validated, so that encoding cannot fail
+    value: Option<Tag>,
+}
+
+impl Nbt {
+    pub fn value(&self) -> Option<&Tag> {
+        self.value.as_ref()
+    }
+}
+
+impl TryFrom<Tag> for Nbt {
+    type Error = DatatypeError;
+
+    fn try_from(v: Tag) -> Result<Self, Self::Error> {
+        v.validate()?;
+        Ok(Nbt { value: Some(v) })
+    }
+}
+
+impl Encode for Nbt {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match &self.value {
+            Some(tag) => {
+                buf.push(tag.type_id());
+                tag.write_payload(buf);
+            }
+            None => buf.push(Tag::END),
+        }
+    }
+}
+
+impl Decode for Nbt {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Nbt> for Vec<u8> {
+    fn from(v: Nbt) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Nbt, DatatypeError> {
+    let type_id = read_array::<1>(stream)?[0];
+    if type_id == Tag::END {
+        return Ok(Nbt::default());
+    }
+    // anything read is valid to write back
+    Ok(Nbt {
+        value: Some(Tag::read_payload(type_id, stream, 0)?),
+    })
+}
+
+// file format (and network NBT before 1.20.2): a named root compound, uncompressed
+pub fn read_named_from_stream(stream: &mut impl Read) -> Result<(String, Tag), DatatypeError> {
+    let type_id = read_array::<1>(stream)?[0];
+    if type_id != Tag::COMPOUND {
+        return Err(DatatypeError::ConvertError);
+    }
+    let name = read_string(stream)?;
+    Ok((name, Tag::read_payload(type_id, stream, 0)?))
+}
+
+pub fn write_named(name: &str, tag: &Tag, buf: &mut Vec<u8>) -> Result<(), DatatypeError> {
+    check_string(name)?;
+    tag.validate()?;
+    buf.push(tag.type_id());
+    write_string(name, buf);
+    tag.write_payload(buf);
+    Ok(())
+}
+
+fn read_array<const N: usize>(stream: &mut impl Read) -> Result<[u8; N], DatatypeError> {
+    let mut bytes = [0; N];
+    match stream.read_exact(&mut bytes) {
+        Ok(_) => Ok(bytes),
+        Err(_) => Err(DatatypeError::ReadError),
+    }
+}
+
+fn read_length(stream: &mut impl Read) -> Result<usize, DatatypeError> {
+    let length = i32::from_be_bytes(read_array(stream)?);
+    usize::try_from(length).map_err(|_| DatatypeError::ConvertError)
+}
+
+// strings are Java's modified UTF-8 prefixed with an unsigned short
+fn check_string(s: &str) -> Result<(), DatatypeError> {
+    let length: usize = s
+        .encode_utf16()
+        .map(|unit| match unit {
+            0x0001..=0x007f => 1,
+            0x0000 | 0x0080..=0x07ff => 2,
+            _ => 3,
+        })
+        .sum();
+    if length > u16::MAX as usize {
+        return Err(DatatypeError::TooLongStringError);
+    }
+    Ok(())
+}
+
+// must be checked first
+fn write_string(s: &str, buf: &mut Vec<u8>) {
+    let mut bytes: Vec<u8> = Vec::new();
+    for unit in s.encode_utf16() {
+        match unit {
+            0x0001..=0x007f => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    buf.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&bytes);
+}
+
+fn read_string(stream: &mut impl Read) -> Result<String, DatatypeError> {
+    let length = u16::from_be_bytes(read_array(stream)?) as usize;
+    let mut bytes: Vec<u8> = Vec::new();
+    if stream.take(length as u64).read_to_end(&mut bytes).ok() != Some(length) {
+        return Err(DatatypeError::ReadError);
+    }
+
+    let mut units: Vec<u16> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i] as u16;
+        let continuation = |j: usize| match bytes.get(j) {
+            Some(&c) if c & 0xc0 == 0x80 => Ok((c & 0x3f) as u16),
+            _ => Err(DatatypeError::ConvertError),
+        };
+        if b & 0x80 == 0 {
+            units.push(b);
+            i += 1;
+        } else if b & 0xe0 == 0xc0 {
+            units.push(((b & 0x1f) << 6) | continuation(i + 1)?);
+            i += 2;
+        } else if b & 0xf0 == 0xe0 {
+            units.push(((b & 0x0f) << 12) | (continuation(i + 1)? << 6) | continuation(i + 2)?);
+            i += 3;
+        } else {
+            return Err(DatatypeError::ConvertError);
+        }
+    }
+    String::from_utf16(&units).map_err(|_| DatatypeError::ConvertError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::VecDeque;
+
+    // hello_world.nbt from https://wiki.vg/NBT
+    const HELLO_WORLD: [u8; 33] = [
+        0x0a, 0x00, 0x0b, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x77, 0x6f, 0x72, 0x6c, 0x64, 0x08,
+        0x00, 0x04, 0x6e, 0x61, 0x6d, 0x65, 0x00, 0x09, 0x42, 0x61, 0x6e, 0x61, 0x6e, 0x72, 0x61,
+        0x6d, 0x61, 0x00,
+    ];
+
+    fn hello_world() -> Tag {
+        Tag::Compound(vec![(
+            "name".to_string(),
+            Tag::String("Bananrama".to_string()),
+        )])
+    }
+
+    fn all_types() -> Tag {
+        Tag::Compound(vec![
+            ("byte".to_string(), Tag::Byte(-1)),
+            ("short".to_string(), Tag::Short(-300)),
+            ("int".to_string(), Tag::Int(25565)),
+            ("long".to_string(), Tag::Long(i64::MIN)),
+            ("float".to_string(), Tag::Float(0.5)),
+            ("double".to_string(), Tag::Double(-1.25)),
+            ("byteArray".to_string(), Tag::ByteArray(vec![0, -128, 127])),
+            ("string".to_string(), Tag::String("\0é☃😀".to_string())),
+            ("emptyList".to_string(), Tag::List(vec![])),
+            (
+                "list".to_string(),
+                Tag::List(vec![Tag::Compound(vec![]), Tag::Compound(vec![])]),
+            ),
+            ("intArray".to_string(), Tag::IntArray(vec![1, -1])),
+            ("longArray".to_string(), Tag::LongArray(vec![i64::MAX])),
+        ])
+    }
+
+    #[test]
+    fn test_read_named_from_stream() {
+        let mut bytes: VecDeque<u8> = VecDeque::from(HELLO_WORLD.to_vec());
+        assert_eq!(
+            read_named_from_stream(&mut bytes),
+            Ok(("hello world".to_string(), hello_world()))
+        );
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_write_named() {
+        let mut bytes: Vec<u8> = Vec::new();
+        write_named("hello world", &hello_world(), &mut bytes).unwrap();
+        assert_eq!(bytes, HELLO_WORLD);
+    }
+
+    #[test]
+    fn test_bigtest() {
+        // bigtest.nbt from https://wiki.vg/NBT, gunzipped
+        let bigtest = include_bytes!("testdata/bigtest.nbt");
+        let mut bytes: VecDeque<u8> = VecDeque::from(bigtest.to_vec());
+        let (name, tag) = read_named_from_stream(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+        assert_eq!(name, "Level");
+        assert_eq!(tag.get("longTest"), Some(&Tag::Long(i64::MAX)));
+        assert_eq!(tag.get("shortTest"), Some(&Tag::Short(i16::MAX)));
+        assert_eq!(tag.get("floatTest"), Some(&Tag::Float(0.49823147)));
+        assert_eq!(
+            tag.get("stringTest"),
+            Some(&Tag::String(
+                "HELLO WORLD THIS IS A TEST STRING ÅÄÖ!".to_string()
+            ))
+        );
+        assert_eq!(
+            tag.get("listTest (long)"),
+            Some(&Tag::List((11..=15).map(Tag::Long).collect()))
+        );
+        assert_eq!(
+            tag.get("nested compound test")
+                .and_then(|t| t.get("egg"))
+                .and_then(|t| t.get("value")),
+            Some(&Tag::Float(0.5))
+        );
+        let byte_array: Vec<i8> = (0..1000)
+            .map(|n: i32| ((n * n * 255 + n * 7) % 100) as i8)
+            .collect();
+        assert_eq!(
+            tag.get("byteArrayTest (the first 1000 values of (n*n*255+n*7)%100, starting with n=0 (0, 62, 34, 16, 8, ...))"),
+            Some(&Tag::ByteArray(byte_array))
+        );
+
+        let mut written: Vec<u8> = Vec::new();
+        write_named(&name, &tag, &mut written).unwrap();
+        assert_eq!(written, bigtest);
+    }
+
+    #[test]
+    fn test_registry_data() {
+        // part of the registry codec as sent in Registry Data since 1.20.2
+        let registry_data = include_bytes!("testdata/registry_data.nbt");
+        let mut bytes: VecDeque<u8> = VecDeque::from(registry_data.to_vec());
+        let nbt = read_from_stream(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+        let element = |name: &str, id: i32, message_id: &str| {
+            json!({
+                "name": name,
+                "id": id,
+                "element": {
+                    "message_id": message_id,
+                    "scaling": "when_caused_by_living_non_player",
+                    "exhaustion": 0.1f32,
+                },
+            })
+        };
+        assert_eq!(
+            Value::from(nbt.value().unwrap()),
+            json!({
+                "minecraft:damage_type": {
+                    "type": "minecraft:damage_type",
+                    "value": [
+                        element("minecraft:in_fire", 0, "inFire"),
+                        element("minecraft:lightning_bolt", 1, "lightningBolt"),
+                    ],
+                },
+            })
+        );
+        assert_eq!(Vec::<u8>::from(nbt), registry_data);
+    }
+
+    #[test]
+    fn test_network_nbt() {
+        // the same compound without the root name
+        let network: Vec<u8> = [&HELLO_WORLD[..1], &HELLO_WORLD[14..]].concat();
+        assert_eq!(
+            Vec::<u8>::from(Nbt::try_from(hello_world()).unwrap()),
+            network
+        );
+
+        let mut bytes: VecDeque<u8> = VecDeque::from(network);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Ok(Nbt::try_from(hello_world()).unwrap())
+        );
+
+        // 1.20.3+ text component sent as a bare string tag
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x08, 0x00, 0x02, 0x68, 0x69]);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Nbt::try_from(Tag::String("hi".to_string()))
+        );
+
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x00]);
+        assert_eq!(read_from_stream(&mut bytes), Ok(Nbt::default()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes: Vec<u8> = Nbt::try_from(all_types()).unwrap().into();
+        let mut bytes: VecDeque<u8> = VecDeque::from(bytes);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Ok(Nbt::try_from(all_types()).unwrap())
+        );
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_modified_utf8() {
+        let mut bytes: Vec<u8> = Vec::new();
+        write_string("\0😀", &mut bytes);
+        assert_eq!(
+            bytes,
+            [0x00, 0x08, 0xc0, 0x80, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]
+        );
+        let mut bytes: VecDeque<u8> = VecDeque::from(bytes);
+        assert_eq!(read_string(&mut bytes), Ok("\0😀".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_write() {
+        let too_long = "é".repeat(u16::MAX as usize / 2 + 1);
+        assert_eq!(
+            Nbt::try_from(Tag::String(too_long.clone())),
+            Err(DatatypeError::TooLongStringError)
+        );
+        let mut bytes: Vec<u8> = Vec::new();
+        assert_eq!(
+            write_named(&too_long, &hello_world(), &mut bytes),
+            Err(DatatypeError::TooLongStringError)
+        );
+        assert!(bytes.is_empty());
+        assert!(Nbt::try_from(Tag::String("é".repeat(u16::MAX as usize / 2))).is_ok());
+
+        let mixed = Tag::List(vec![Tag::Int(1), Tag::Long(1)]);
+        assert_eq!(
+            Nbt::try_from(Tag::Compound(vec![("list".to_string(), mixed)])),
+            Err(DatatypeError::ConvertError)
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        // unknown tag type
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x0d, 0x00]);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Err(DatatypeError::ConvertError)
+        );
+        // negative array length
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x07, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Err(DatatypeError::ConvertError)
+        );
+        // truncated compound
+        let mut bytes: VecDeque<u8> = VecDeque::from(HELLO_WORLD[..20].to_vec());
+        assert_eq!(
+            read_named_from_stream(&mut bytes),
+            Err(DatatypeError::ReadError)
+        );
+        // nested too deep
+        let mut bytes: VecDeque<u8> =
+            VecDeque::from([&[0x09][..], &[0x09, 0x00, 0x00, 0x00, 0x01].repeat(600)[..]].concat());
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Err(DatatypeError::ConvertError)
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            Value::from(&all_types()),
+            json!({
+                "byte": -1,
+                "short": -300,
+                "int": 25565,
+                "long": i64::MIN,
+                "float": 0.5,
+                "double": -1.25,
+                "byteArray": [0, -128, 127],
+                "string": "\0é☃😀",
+                "emptyList": [],
+                "list": [{}, {}],
+                "intArray": [1, -1],
+                "longArray": [i64::MAX],
+            })
+        );
+
+        let tag = Tag::try_from(&json!({
+            "text": "Hello",
+            "bold": true,
+            "extra": [{"text": "!"}],
+            "big": 4294967296i64,
+            "scale": 1.5,
+        }))
+        .unwrap();
+        assert_eq!(tag.get("text"), Some(&Tag::String("Hello".to_string())));
+        assert_eq!(tag.get("bold"), Some(&Tag::Byte(1)));
+        assert_eq!(
+            tag.get("extra"),
+            Some(&Tag::List(vec![Tag::Compound(vec![(
+                "text".to_string(),
+                Tag::String("!".to_string())
+            )])]))
+        );
+        assert_eq!(tag.get("big"), Some(&Tag::Long(4294967296)));
+        assert_eq!(tag.get("scale"), Some(&Tag::Double(1.5)));
+
+        assert!(Tag::try_from(&json!([1, "a"])).is_err());
+        assert!(Tag::try_from(&json!(null)).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use super::{boolean, DatatypeError, Decode, Encode};
+use std::io::Read;
+
+// prefixed with a Boolean telling whether the value is present
+#[derive(Debug, PartialEq, Clone)]
+pub struct Optional<T> {
+    pub value: Option<T>,
+}
+
+impl<T> From<Option<T>> for Optional<T> {
+    fn from(v: Option<T>) -> Self {
+        Optional { value: v }
+    }
+}
+
+impl<T: Encode> Encode for Optional<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        boolean::Boolean::from(self.value.is_some()).encode(buf);
+        if let Some(value) = &self.value {
+            value.encode(buf);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Optional<T> {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl<T: Encode> From<Optional<T>> for Vec<u8> {
+    fn from(v: Optional<T>) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream<T: Decode>(stream: &mut impl Read) -> Result<Optional<T>, DatatypeError> {
+    if boolean::read_from_stream(stream)?.value {
+        Ok(Optional::from(Some(T::decode(stream)?)))
+    } else {
+        Ok(Optional::from(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::datatype::uuid;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_round_trip() {
+        for v in [
+            None,
+            Some(uuid::UUID::from(0x069a79f444e94726a5befca90e38aaf5)),
+        ] {
+            let optional = Optional::from(v);
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(optional.clone()));
+            assert_eq!(read_from_stream(&mut bytes), Ok(optional));
+            assert!(bytes.is_empty());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use super::{long, DatatypeError, Decode, Encode};
+use std::io::Read;
+
+// x: 26 bits, z: 26 bits, y: 12 bits packed into a Long
+#[derive(Debug, PartialEq, Clone)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Position {
+        Position { x, y, z }
+    }
+}
+
+impl From<i64> for Position {
+    fn from(v: i64) -> Self {
+        Position {
+            x: (v >> 38) as i32,
+            y: (v << 52 >> 52) as i32,
+            z: (v << 26 >> 38) as i32,
+        }
+    }
+}
+
+impl From<&Position> for i64 {
+    fn from(v: &Position) -> Self {
+        ((v.x as i64 & 0x3ff_ffff) << 38) | ((v.z as i64 & 0x3ff_ffff) << 12) | (v.y as i64 & 0xfff)
+    }
+}
+
+impl Encode for Position {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        long::Long::from(i64::from(self)).encode(buf);
+    }
+}
+
+impl Decode for Position {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Position> for Vec<u8> {
+    fn from(v: Position) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Position, DatatypeError> {
+    Ok(Position::from(long::read_from_stream(stream)?.value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        // https://wiki.vg/Protocol#Position
+        let bytes: Vec<u8> = Position::new(18357644, 831, -20882616).into();
+        assert_eq!(bytes, [0x46, 0x07, 0x63, 0x2c, 0x15, 0xb4, 0x83, 0x3f]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for position in [
+            Position::new(0, 0, 0),
+            Position::new(-1, -1, -1),
+            Position::new(33554431, 2047, 33554431),
+            Position::new(-33554432, -2048, -33554432),
+            Position::new(123, -64, -456),
+        ] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(position.clone()));
+            assert_eq!(read_from_stream(&mut bytes), Ok(position));
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::{varint, DatatypeError, Decode, Encode};
+use std::io::Read;
+
+// elements prefixed with their count as a VarInt
+#[derive(Debug, PartialEq, Clone)]
+pub struct PrefixedArray<T> {
+    pub value: Vec<T>,
+}
+
+impl<T> From<Vec<T>> for PrefixedArray<T> {
+    fn from(v: Vec<T>) -> Self {
+        PrefixedArray { value: v }
+    }
+}
+
+impl<T: Encode> Encode for PrefixedArray<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        varint::VarInt::from(self.value.len() as i32).encode(buf);
+        for element in &self.value {
+            element.encode(buf);
+        }
+    }
+}
+
+impl<T: Decode> Decode for PrefixedArray<T> {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl<T: Encode> From<PrefixedArray<T>> for Vec<u8> {
+    fn from(v: PrefixedArray<T>) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream<T: Decode>(
+    stream: &mut impl Read,
+) -> Result<PrefixedArray<T>, DatatypeError> {
+    let length = varint::read_from_stream(stream)?;
+    if length.value < 0 {
+        return Err(DatatypeError::NegativeLength);
+    }
+    // the count is not trusted for preallocation
+    let mut value: Vec<T> = Vec::new();
+    for _ in 0..length.value {
+        value.push(T::decode(stream)?);
+    }
+    Ok(PrefixedArray { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::datatype::string;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let array = PrefixedArray::from(vec![varint::VarInt::from(1), varint::VarInt::from(300)]);
+        let bytes: Vec<u8> = array.into();
+        assert_eq!(bytes, [0x02, 0x01, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let array = PrefixedArray::from(vec![
+            string::String::from("minecraft:overworld"),
+            string::String::from(""),
+        ]);
+        let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(array.clone()));
+        assert_eq!(read_from_stream(&mut bytes), Ok(array));
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_read_from_stream_negative_length() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert_eq!(
+            read_from_stream::<string::String>(&mut bytes),
+            Err(DatatypeError::NegativeLength)
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Short {
+    pub value: i16,
+}
+
+impl From<i16> for Short {
+    fn from(v: i16) -> Self {
+        Short { value: v }
+    }
+}
+
+impl Encode for Short {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for Short {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<Short> for Vec<u8> {
+    fn from(v: Short) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<Short, DatatypeError> {
+    let mut bytes: [u8; 2] = [0; 2];
+    if stream.read_exact(&mut bytes[..]).is_ok() {
+        Ok(Short::from(i16::from_be_bytes(bytes)))
+    } else {
+        Err(DatatypeError::ReadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let bytes: Vec<u8> = Short::from(-2).into();
+        assert_eq!(bytes, [0xff, 0xfe]);
+    }
+
+    #[test]
+    fn test_read_from_stream() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x01, 0x00]);
+        assert_eq!(read_from_stream(&mut bytes), Ok(Short::from(256)));
+    }
+}
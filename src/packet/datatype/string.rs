@@ -1,4 +1,4 @@
-use super::{varint, DatatypeError};
+use super::{varint, DatatypeError, Decode, Encode};
 use std::io::Read;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -7,6 +7,7 @@ pub struct String {
 }
 
 impl String {
+    // in UTF-16 code units, as counted by Java
     pub const MAX_LENGTH: i32 = 32767;
 }
 
@@ -24,36 +25,58 @@ impl From<std::string::String> for String {
     }
 }
 
-impl Into<Vec<u8>> for String {
-    fn into(self) -> Vec<u8> {
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        varint::VarInt::from(self.value.len() as i32).encode(buf);
+        buf.extend_from_slice(self.value.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<String> for Vec<u8> {
+    fn from(v: String) -> Self {
         let mut res: Vec<u8> = Vec::new();
-        let mut bytes = self.value.into_bytes();
-        let length: varint::VarInt = (bytes.len() as i32).into();
-        res.append(&mut length.into());
-        res.append(&mut bytes);
+        v.encode(&mut res);
         res
     }
 }
 
 pub fn read_from_stream(stream: &mut impl Read) -> Result<String, DatatypeError> {
-    let length = varint::read_from_stream(stream).unwrap();
-    if length.value > String::MAX_LENGTH {
+    read_with_max_length(stream, String::MAX_LENGTH)
+}
+
+// the byte length may be up to 3 times the maximum length, since one UTF-16 unit takes up to 3 bytes
+pub fn read_with_max_length(
+    stream: &mut impl Read,
+    max_length: i32,
+) -> Result<String, DatatypeError> {
+    let length = varint::read_from_stream(stream)?;
+    if length.value < 0 {
+        return Err(DatatypeError::NegativeLength);
+    }
+    if length.value as i64 > max_length as i64 * 3 {
         return Err(DatatypeError::TooLongStringError);
     }
 
     let mut bytes: Vec<u8> = Vec::new();
-    let mut byte = [0; 1];
-    for _ in 0..length.value {
-        match stream.read_exact(&mut byte[..]) {
-            Ok(_) => bytes.push(byte[0]),
-            Err(_) => return Err(DatatypeError::ReadError),
-        }
+    match stream.take(length.value as u64).read_to_end(&mut bytes) {
+        Ok(n) if n == length.value as usize => {}
+        _ => return Err(DatatypeError::ReadError),
     }
 
-    return match std::string::String::from_utf8(bytes) {
-        Ok(s) => Ok(String { value: s }),
-        Err(_) => Err(DatatypeError::ConvertError),
+    let value = match std::string::String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => return Err(DatatypeError::ConvertError),
     };
+    if value.encode_utf16().count() > max_length as usize {
+        return Err(DatatypeError::TooLongStringError);
+    }
+    Ok(String { value })
 }
 
 #[cfg(test)]
@@ -85,4 +108,44 @@ mod tests {
         ]);
         assert_eq!(s_into, bytes);
     }
+
+    #[test]
+    fn test_read_with_max_length() {
+        // "🌟" is 4 bytes but 2 UTF-16 units
+        let bytes: Vec<u8> = String::from("ab🌟").into();
+        let mut stream: VecDeque<u8> = VecDeque::from(bytes.clone());
+        assert_eq!(
+            read_with_max_length(&mut stream, 4),
+            Ok(String::from("ab🌟"))
+        );
+        let mut stream: VecDeque<u8> = VecDeque::from(bytes);
+        assert_eq!(
+            read_with_max_length(&mut stream, 3),
+            Err(DatatypeError::TooLongStringError)
+        );
+
+        // rejected before the body is read
+        let mut stream: VecDeque<u8> = VecDeque::from([0x31, b'a']);
+        assert_eq!(
+            read_with_max_length(&mut stream, 16),
+            Err(DatatypeError::TooLongStringError)
+        );
+        assert_eq!(stream.len(), 1);
+    }
+
+    #[test]
+    fn test_read_from_stream_invalid_length() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Err(DatatypeError::NegativeLength)
+        );
+        let mut bytes: VecDeque<u8> = VecDeque::from([0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Err(DatatypeError::VarIntTooBig)
+        );
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x80]);
+        assert_eq!(read_from_stream(&mut bytes), Err(DatatypeError::ReadError));
+    }
 }
@@ -0,0 +1,63 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnsignedByte {
+    pub value: u8,
+}
+
+impl From<u8> for UnsignedByte {
+    fn from(v: u8) -> Self {
+        UnsignedByte { value: v }
+    }
+}
+
+impl Encode for UnsignedByte {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for UnsignedByte {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<UnsignedByte> for Vec<u8> {
+    fn from(v: UnsignedByte) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<UnsignedByte, DatatypeError> {
+    let mut bytes: [u8; 1] = [0; 1];
+    if stream.read_exact(&mut bytes[..]).is_ok() {
+        Ok(UnsignedByte::from(u8::from_be_bytes(bytes)))
+    } else {
+        Err(DatatypeError::ReadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let bytes: Vec<u8> = UnsignedByte::from(200).into();
+        assert_eq!(bytes, [0xc8]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [0, 1, 127, 128, u8::MAX] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(UnsignedByte::from(v)));
+            assert_eq!(read_from_stream(&mut bytes), Ok(UnsignedByte::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::DatatypeError;
+use super::{DatatypeError, Decode, Encode};
 use std::io::Read;
 
 #[derive(Debug)]
@@ -6,13 +6,46 @@ pub struct UnsignedShort {
     pub value: u16,
 }
 
+impl From<u16> for UnsignedShort {
+    fn from(v: u16) -> Self {
+        UnsignedShort { value: v }
+    }
+}
+
+impl Encode for UnsignedShort {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for UnsignedShort {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
 pub fn read_from_stream(stream: &mut impl Read) -> Result<UnsignedShort, DatatypeError> {
     let mut bytes: [u8; 2] = [0; 2];
-    return if let Ok(_) = stream.read_exact(&mut bytes[..]) {
+    if stream.read_exact(&mut bytes[..]).is_ok() {
         Ok(UnsignedShort {
             value: ((bytes[0] as u16) << 8) | (bytes[1] as u16),
         })
     } else {
         Err(DatatypeError::ReadError)
-    };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_encode_decode() {
+        let mut bytes: Vec<u8> = Vec::new();
+        UnsignedShort::from(25565).encode(&mut bytes);
+        assert_eq!(bytes, [0x63, 0xdd]);
+        let mut stream: VecDeque<u8> = VecDeque::from(bytes);
+        assert_eq!(UnsignedShort::decode(&mut stream).unwrap().value, 25565);
+    }
 }
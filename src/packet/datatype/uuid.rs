@@ -1,7 +1,8 @@
-use super::DatatypeError;
+use super::{DatatypeError, Decode, Encode};
 use std::io::Read;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct UUID {
     pub value: u128,
 }
@@ -20,9 +21,21 @@ impl From<u128> for UUID {
     }
 }
 
+impl Encode for UUID {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.value.to_be_bytes());
+    }
+}
+
+impl Decode for UUID {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
 pub fn read_from_stream(stream: &mut impl Read) -> Result<UUID, DatatypeError> {
     let mut bytes: [u8; 16] = [0; 16];
-    if let Ok(_) = stream.read_exact(&mut bytes[..]) {
+    if stream.read_exact(&mut bytes[..]).is_ok() {
         Ok(UUID::from(&bytes))
     } else {
         Err(DatatypeError::ReadError)
@@ -45,4 +58,17 @@ mod tests {
             Ok(UUID::from(0x0123456789abcdeffedcba9876543210))
         );
     }
+
+    #[test]
+    fn test_encode() {
+        let mut bytes: Vec<u8> = Vec::new();
+        UUID::from(0x0123456789abcdeffedcba9876543210).encode(&mut bytes);
+        assert_eq!(
+            bytes,
+            [
+                0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54,
+                0x32, 0x10,
+            ]
+        );
+    }
 }
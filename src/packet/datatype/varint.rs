@@ -1,4 +1,4 @@
-use super::DatatypeError;
+use super::{DatatypeError, Decode, Encode};
 use std::io::Read;
 use std::result::Result;
 
@@ -10,6 +10,7 @@ pub struct VarInt {
 impl VarInt {
     const SEGMENT_BITS: u8 = 0b0111_1111;
     const CONTINUE_BIT: u8 = 0b1000_0000;
+    const MAX_BYTES: usize = 5;
 }
 
 impl From<&[u8]> for VarInt {
@@ -33,9 +34,8 @@ impl From<i32> for VarInt {
     }
 }
 
-impl Into<Vec<u8>> for VarInt {
-    fn into(self) -> Vec<u8> {
-        let mut res: Vec<u8> = Vec::new();
+impl Encode for VarInt {
+    fn encode(&self, buf: &mut Vec<u8>) {
         let mut value: u32 = if self.value < 0 {
             u32::from_be_bytes(self.value.to_be_bytes())
         } else {
@@ -43,14 +43,27 @@ impl Into<Vec<u8>> for VarInt {
         };
 
         loop {
-            if ((value as u8) & !Self::SEGMENT_BITS) == 0 {
-                res.push(value as u8);
+            if (value & !(VarInt::SEGMENT_BITS as u32)) == 0 {
+                buf.push(value as u8);
                 break;
             } else {
-                res.push(((value as u8) & Self::SEGMENT_BITS) | Self::CONTINUE_BIT);
+                buf.push(((value as u8) & VarInt::SEGMENT_BITS) | VarInt::CONTINUE_BIT);
                 value >>= 7;
             }
         }
+    }
+}
+
+impl Decode for VarInt {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<VarInt> for Vec<u8> {
+    fn from(v: VarInt) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
         res
     }
 }
@@ -59,7 +72,11 @@ pub fn read_from_stream(stream: &mut impl Read) -> Result<VarInt, DatatypeError>
     let mut varint_bytes: Vec<u8> = Vec::new();
     let mut byte = [0; 1];
     loop {
-        if let Ok(_) = stream.read_exact(&mut byte[..]) {
+        if varint_bytes.len() == VarInt::MAX_BYTES {
+            return Err(DatatypeError::VarIntTooBig);
+        }
+
+        if stream.read_exact(&mut byte[..]).is_ok() {
             varint_bytes.push(byte[0]);
         } else {
             return Err(DatatypeError::ReadError);
@@ -74,7 +91,8 @@ pub fn read_from_stream(stream: &mut impl Read) -> Result<VarInt, DatatypeError>
 
 #[cfg(test)]
 mod test {
-    use super::VarInt;
+    use super::*;
+    use std::collections::VecDeque;
 
     #[test]
     fn test_into_positive() {
@@ -90,10 +108,44 @@ mod test {
         assert_eq!(vec![255, 255, 255, 255, 15], vi_negative_into);
     }
 
+    #[test]
+    fn test_into_multi_byte() {
+        let vi_multi_byte: VarInt = VarInt::from(256);
+        let vi_multi_byte_into: Vec<u8> = vi_multi_byte.into();
+        assert_eq!(vec![128, 2], vi_multi_byte_into);
+
+        let vi_max: VarInt = VarInt::from(2147483647);
+        let vi_max_into: Vec<u8> = vi_max.into();
+        assert_eq!(vec![255, 255, 255, 255, 7], vi_max_into);
+    }
+
     #[test]
     fn test_into_zero() {
         let vi_zero: VarInt = VarInt::from(0);
         let vi_zero_into: Vec<u8> = vi_zero.into();
         assert_eq!(vec![0], vi_zero_into);
     }
+
+    #[test]
+    fn test_read_from_stream() {
+        let mut bytes: VecDeque<u8> =
+            VecDeque::from([0xff, 0xff, 0xff, 0xff, 0x0f, 0xdd, 0xc7, 0x01]);
+        assert_eq!(read_from_stream(&mut bytes).unwrap().value, -1);
+        assert_eq!(read_from_stream(&mut bytes).unwrap().value, 25565);
+        assert_eq!(
+            read_from_stream(&mut bytes).unwrap_err(),
+            DatatypeError::ReadError
+        );
+    }
+
+    #[test]
+    fn test_read_from_stream_too_big() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0xff; 6]);
+        assert_eq!(
+            read_from_stream(&mut bytes).unwrap_err(),
+            DatatypeError::VarIntTooBig
+        );
+        // the sixth byte is never read
+        assert_eq!(bytes.len(), 1);
+    }
 }
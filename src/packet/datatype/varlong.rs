@@ -0,0 +1,99 @@
+use super::{DatatypeError, Decode, Encode};
+use std::io::Read;
+use std::result::Result;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct VarLong {
+    pub value: i64,
+}
+
+impl VarLong {
+    const SEGMENT_BITS: u8 = 0b0111_1111;
+    const CONTINUE_BIT: u8 = 0b1000_0000;
+    const MAX_BYTES: usize = 10;
+}
+
+impl From<i64> for VarLong {
+    fn from(v: i64) -> Self {
+        VarLong { value: v }
+    }
+}
+
+impl Encode for VarLong {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let mut value = self.value as u64;
+        loop {
+            if (value & !(VarLong::SEGMENT_BITS as u64)) == 0 {
+                buf.push(value as u8);
+                break;
+            } else {
+                buf.push(((value as u8) & VarLong::SEGMENT_BITS) | VarLong::CONTINUE_BIT);
+                value >>= 7;
+            }
+        }
+    }
+}
+
+impl Decode for VarLong {
+    fn decode(stream: &mut impl Read) -> Result<Self, DatatypeError> {
+        read_from_stream(stream)
+    }
+}
+
+impl From<VarLong> for Vec<u8> {
+    fn from(v: VarLong) -> Self {
+        let mut res: Vec<u8> = Vec::new();
+        v.encode(&mut res);
+        res
+    }
+}
+
+pub fn read_from_stream(stream: &mut impl Read) -> Result<VarLong, DatatypeError> {
+    let mut value: u64 = 0;
+    let mut byte = [0; 1];
+    for i in 0..VarLong::MAX_BYTES {
+        if stream.read_exact(&mut byte[..]).is_err() {
+            return Err(DatatypeError::ReadError);
+        }
+        value |= ((byte[0] & VarLong::SEGMENT_BITS) as u64) << (7 * i);
+        if byte[0] & VarLong::CONTINUE_BIT == 0 {
+            return Ok(VarLong::from(value as i64));
+        }
+    }
+    Err(DatatypeError::VarIntTooBig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_into() {
+        let bytes: Vec<u8> = VarLong::from(2147483648).into();
+        assert_eq!(bytes, [0x80, 0x80, 0x80, 0x80, 0x08]);
+        let bytes: Vec<u8> = VarLong::from(-1).into();
+        assert_eq!(
+            bytes,
+            [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [i64::MIN, -1, 0, 1, 127, 128, 2147483647, i64::MAX] {
+            let mut bytes: VecDeque<u8> = VecDeque::from(Vec::<u8>::from(VarLong::from(v)));
+            assert_eq!(read_from_stream(&mut bytes), Ok(VarLong::from(v)));
+            assert!(bytes.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_read_from_stream_too_long() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0xff; 11]);
+        assert_eq!(
+            read_from_stream(&mut bytes),
+            Err(DatatypeError::VarIntTooBig)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use super::{Result, ServerBoundPacketBody};
+use crate::session::{Session, SessionState};
+use std::io::Read;
+
+// https://wiki.vg/Protocol_version_numbers
+pub const PROTOCOL_1_7_6: i32 = 5;
+pub const PROTOCOL_1_13: i32 = 393;
+pub const PROTOCOL_1_19: i32 = 759;
+pub const PROTOCOL_1_19_1: i32 = 760;
+pub const PROTOCOL_1_20_2: i32 = 764;
+pub const PROTOCOL_1_20_5: i32 = 766;
+
+// submitted by #[derive(Packet)] for every server-bound packet
+pub struct ServerBoundRegistration {
+    pub name: &'static str,
+    pub state: SessionState,
+    pub id: i32,
+    pub min_protocol: i32,
+    pub max_length: i32,
+    pub read: fn(&mut Session, &mut dyn Read) -> Result<Box<dyn ServerBoundPacketBody>>,
+}
+
+inventory::collect!(ServerBoundRegistration);
+
+// the protocol version is unknown (None) until the handshake has been read
+pub fn get_server_bound_packet(
+    state: &SessionState,
+    protocol_version: Option<i32>,
+    id: i32,
+) -> Option<&'static ServerBoundRegistration> {
+    inventory::iter::<ServerBoundRegistration>
+        .into_iter()
+        .find(|r| {
+            r.state == *state && r.id == id && protocol_version.is_none_or(|v| v >= r.min_protocol)
+        })
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LoginStartLayout {
+    // name
+    NameOnly,
+    // name, has signature data, [timestamp, public key, signature]
+    SignatureData,
+    // name, has signature data, [timestamp, public key, signature], has UUID, [UUID]
+    SignatureDataAndOptionalUuid,
+    // name, has UUID, [UUID]
+    OptionalUuid,
+    // name, UUID
+    Uuid,
+}
+
+pub fn get_login_start_layout(protocol_version: i32) -> LoginStartLayout {
+    match protocol_version {
+        v if v < PROTOCOL_1_19 => LoginStartLayout::NameOnly,
+        PROTOCOL_1_19 => LoginStartLayout::SignatureData,
+        PROTOCOL_1_19_1 => LoginStartLayout::SignatureDataAndOptionalUuid,
+        v if v < PROTOCOL_1_20_2 => LoginStartLayout::OptionalUuid,
+        _ => LoginStartLayout::Uuid,
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EncryptionResponseLayout {
+    // shared secret and verify token prefixed with a Short
+    ShortPrefixed,
+    // shared secret and verify token prefixed with a VarInt
+    VarIntPrefixed,
+    // shared secret, has verify token, verify token or [salt, message signature]
+    VerifyTokenOrSignature,
+}
+
+pub fn get_encryption_response_layout(protocol_version: i32) -> EncryptionResponseLayout {
+    match protocol_version {
+        v if v <= PROTOCOL_1_7_6 => EncryptionResponseLayout::ShortPrefixed,
+        PROTOCOL_1_19 | PROTOCOL_1_19_1 => EncryptionResponseLayout::VerifyTokenOrSignature,
+        _ => EncryptionResponseLayout::VarIntPrefixed,
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EncryptionRequestLayout {
+    // server id, public key and verify token prefixed with a Short
+    ShortPrefixed,
+    // server id, public key and verify token prefixed with a VarInt
+    VarIntPrefixed,
+    // server id, public key, verify token, should authenticate
+    ShouldAuthenticate,
+}
+
+pub fn get_encryption_request_layout(protocol_version: i32) -> EncryptionRequestLayout {
+    match protocol_version {
+        v if v <= PROTOCOL_1_7_6 => EncryptionRequestLayout::ShortPrefixed,
+        v if v < PROTOCOL_1_20_5 => EncryptionRequestLayout::VarIntPrefixed,
+        _ => EncryptionRequestLayout::ShouldAuthenticate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_of(
+        state: SessionState,
+        protocol_version: Option<i32>,
+        id: i32,
+    ) -> Option<&'static str> {
+        get_server_bound_packet(&state, protocol_version, id).map(|r| r.name)
+    }
+
+    #[test]
+    fn test_get_server_bound_packet() {
+        assert_eq!(
+            name_of(SessionState::HANDSHAKING, None, 0x00),
+            Some("C2SHandshakePacket")
+        );
+        assert_eq!(
+            name_of(SessionState::STATUS, Some(4), 0x01),
+            Some("C2SPingRequestPacket")
+        );
+        assert_eq!(
+            name_of(SessionState::LOGIN, Some(764), 0x02),
+            Some("C2SLoginPluginResponse")
+        );
+        assert_eq!(name_of(SessionState::LOGIN, Some(340), 0x02), None);
+        assert_eq!(name_of(SessionState::STATUS, Some(764), 0x02), None);
+    }
+
+    #[test]
+    fn test_max_length() {
+        let max_length = |state, id| {
+            get_server_bound_packet(&state, Some(765), id)
+                .unwrap()
+                .max_length
+        };
+        assert_eq!(max_length(SessionState::STATUS, 0x00), 1);
+        assert_eq!(max_length(SessionState::STATUS, 0x01), 9);
+        assert!(max_length(SessionState::LOGIN, 0x00) < 5000);
+    }
+
+    #[test]
+    fn test_layouts() {
+        // 1.7.10, 1.8.9, 1.12.2
+        assert_eq!(get_login_start_layout(5), LoginStartLayout::NameOnly);
+        assert_eq!(get_login_start_layout(47), LoginStartLayout::NameOnly);
+        assert_eq!(get_login_start_layout(340), LoginStartLayout::NameOnly);
+        // 1.19, 1.19.2, 1.19.4, 1.20.1, 1.20.2, 1.20.4
+        assert_eq!(get_login_start_layout(759), LoginStartLayout::SignatureData);
+        assert_eq!(
+            get_login_start_layout(760),
+            LoginStartLayout::SignatureDataAndOptionalUuid
+        );
+        assert_eq!(get_login_start_layout(762), LoginStartLayout::OptionalUuid);
+        assert_eq!(get_login_start_layout(763), LoginStartLayout::OptionalUuid);
+        assert_eq!(get_login_start_layout(764), LoginStartLayout::Uuid);
+        assert_eq!(get_login_start_layout(765), LoginStartLayout::Uuid);
+
+        assert_eq!(
+            get_encryption_response_layout(4),
+            EncryptionResponseLayout::ShortPrefixed
+        );
+        assert_eq!(
+            get_encryption_response_layout(47),
+            EncryptionResponseLayout::VarIntPrefixed
+        );
+        assert_eq!(
+            get_encryption_response_layout(760),
+            EncryptionResponseLayout::VerifyTokenOrSignature
+        );
+        assert_eq!(
+            get_encryption_response_layout(761),
+            EncryptionResponseLayout::VarIntPrefixed
+        );
+
+        assert_eq!(
+            get_encryption_request_layout(5),
+            EncryptionRequestLayout::ShortPrefixed
+        );
+        assert_eq!(
+            get_encryption_request_layout(765),
+            EncryptionRequestLayout::VarIntPrefixed
+        );
+        assert_eq!(
+            get_encryption_request_layout(767),
+            EncryptionRequestLayout::ShouldAuthenticate
+        );
+    }
+}
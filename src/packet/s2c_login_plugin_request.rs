@@ -0,0 +1,36 @@
+use super::datatype::{string, varint};
+use super::registry;
+use super::{PacketBody, Result};
+use crate::encryption;
+use crate::session::Session;
+use fake_minecraft_server_derive::Packet;
+use std::fmt::Debug;
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x04, direction = Clientbound, min_protocol = registry::PROTOCOL_1_13)]
+pub struct S2CLoginPluginRequest {
+    pub message_id: varint::VarInt,
+    pub channel: string::String,
+    pub data: Vec<u8>,
+}
+
+impl S2CLoginPluginRequest {
+    const VELOCITY_CHANNEL: &'static str = "velocity:player_info";
+    const VELOCITY_MODERN_DEFAULT: u8 = 0x01;
+
+    pub fn velocity_player_info() -> Result<S2CLoginPluginRequest> {
+        let message_id = i32::from_be_bytes(encryption::generate_verify_token()?) & i32::MAX;
+
+        Ok(S2CLoginPluginRequest {
+            message_id: varint::VarInt::from(message_id),
+            channel: string::String::from(Self::VELOCITY_CHANNEL),
+            data: vec![Self::VELOCITY_MODERN_DEFAULT],
+        })
+    }
+}
+
+impl PacketBody for S2CLoginPluginRequest {
+    fn update_session(&self, session: &mut Session) {
+        session.plugin_message_id = Some(self.message_id.value);
+    }
+}
@@ -1,81 +1,49 @@
-use super::datatype::{string, varint};
-use super::{ClientBoundPacketBody, PacketBody, PacketError, Result};
+use super::datatype::string;
+use super::PacketBody;
 use crate::session::Session;
-use crate::CONFIG;
-use std::io::Write;
-
-#[derive(Debug)]
-pub struct S2CStatusResponsePacket {}
+use crate::virtual_host::Profile;
+use fake_minecraft_server_derive::Packet;
+use serde_json::json;
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x00, direction = Clientbound)]
+pub struct S2CStatusResponsePacket {
+    response: string::String,
+}
 
 impl S2CStatusResponsePacket {
-    pub const PACKET_ID: i32 = 0x00;
-
-    const DEFAULT_VERSION_NAME: &'static str = "1.20.2";
-    const DEFAULT_VERSION_PROTOCOL: u16 = 764;
-    const DEFAULT_DESCRIPTION: &'static str = "A Minecraft Server";
-    const DEFAULT_PLAYERS_MAX: u16 = 20;
-    const DEFAULT_PLAYERS_ONLINE: u16 = 0;
-
-    pub fn new() -> S2CStatusResponsePacket {
-        S2CStatusResponsePacket {}
+    pub fn new(session: &Session) -> S2CStatusResponsePacket {
+        S2CStatusResponsePacket {
+            response: string::String::from(Self::get_response_json(
+                &session.profile,
+                session.protocol_version,
+            )),
+        }
     }
 
-    fn get_response_json() -> String {
-        let version_name = CONFIG
-            .get::<String>("version_name")
-            .unwrap_or(Self::DEFAULT_VERSION_NAME.into());
-        let version_protocol = CONFIG
-            .get::<u16>("version_protocol")
-            .unwrap_or(Self::DEFAULT_VERSION_PROTOCOL);
-        let description = CONFIG
-            .get::<String>("description")
-            .unwrap_or(Self::DEFAULT_DESCRIPTION.into());
-        let players_max = CONFIG
-            .get::<u16>("players_max")
-            .unwrap_or(Self::DEFAULT_PLAYERS_MAX);
-        let players_online = CONFIG
-            .get::<u16>("players_online")
-            .unwrap_or(Self::DEFAULT_PLAYERS_ONLINE);
+    fn get_response_json(profile: &Profile, protocol_version: Option<i32>) -> String {
+        let mut response = json!({
+            "version": {
+                "name": profile.version_name,
+                "protocol": profile.status_protocol_version(protocol_version),
+            },
+            "enforcesSecureChat": true,
+            "description": {
+                "text": profile.description,
+            },
+            "players": {
+                "max": profile.players_max,
+                "online": profile.players_online,
+            },
+        });
+        if let Some(favicon) = &profile.favicon {
+            response["favicon"] = json!(favicon);
+        }
 
-        format!("{{\"version\":{{\"name\":\"{}\",\"protocol\":{}}},\"enforcesSecureChat\":true,\"description\":{{\"text\":\"{}\"}},\"players\":{{\"max\":{},\"online\":{}}}}}",
-            version_name,
-            version_protocol,
-            description,
-            players_max,
-            players_online
-        )
+        response.to_string()
     }
 }
 
 impl PacketBody for S2CStatusResponsePacket {
     fn update_session(&self, _: &mut Session) {}
 }
-
-impl ClientBoundPacketBody for S2CStatusResponsePacket {
-    fn write_to_stream(&self, _: &mut Session, stream: &mut impl Write) -> Result<()> {
-        let packet_id_bytes: Vec<u8> =
-            varint::VarInt::from(S2CStatusResponsePacket::PACKET_ID).into();
-
-        let response_json_bytes: Vec<u8> = string::String::from(Self::get_response_json()).into();
-
-        let packet_length: usize = packet_id_bytes.len() + response_json_bytes.len();
-        let packet_length_bytes: Vec<u8> = varint::VarInt::from(packet_length as i32).into();
-
-        let bytes: Vec<u8> = [
-            &packet_length_bytes[..],
-            &packet_id_bytes[..],
-            &response_json_bytes[..],
-        ]
-        .concat();
-
-        if stream.write_all(&bytes).is_err() {
-            return Err(PacketError::WriteError.into());
-        }
-
-        if stream.flush().is_err() {
-            return Err(PacketError::FlushError.into());
-        }
-
-        Ok(())
-    }
-}
@@ -0,0 +1,192 @@
+use crate::packet::c2s_handshake::C2SHandshakePacket;
+use crate::packet::c2s_login_start::C2SLoginStartPacket;
+use crate::packet::datatype::{string, unsigned_short, varint};
+use crate::packet::{self, Packet, PacketError, Result};
+use crate::server::ConnectedPlayer;
+use crate::session::Session;
+use log::info;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// re-sends the recorded handshake and login start to the backend, then copies bytes both ways
+// until either side closes the connection
+pub fn splice(session: &Session, client: TcpStream) -> Result<()> {
+    let (Some(backend_address), Some(login_start)) = (
+        &session.server.passthrough_backend,
+        &session.passthrough_login_start,
+    ) else {
+        return Err(PacketError::SequenceError(
+            "Session is not set up for passthrough".to_string(),
+        )
+        .into());
+    };
+
+    let start = Instant::now();
+    let address = backend_address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| PacketError::ReadError(format!("Could not resolve {}", backend_address)))?;
+    let mut backend = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    info!(
+        "[Passthrough] {} -> {} connected in {:.3}s",
+        session.peer_address,
+        address,
+        start.elapsed().as_secs_f64()
+    );
+
+    let mut bytes = packet::encode_packet(
+        C2SHandshakePacket::ID,
+        &[
+            &varint::VarInt::from(session.protocol_version.unwrap_or_default()),
+            &string::String::from(session.server_address.clone().unwrap_or_default()),
+            &unsigned_short::UnsignedShort::from(session.server_port.unwrap_or_default()),
+            &varint::VarInt::from(2),
+        ],
+    );
+    bytes.append(&mut packet::encode_packet(
+        C2SLoginStartPacket::ID,
+        &[login_start],
+    ));
+    backend.write_all(&bytes)?;
+
+    // registered so that the player can be kicked, by the address of the connection itself
+    // as the session may have the address forwarded by a proxy
+    let key = client.peer_addr()?;
+    let connected_players = &session.server.connected_players;
+    connected_players.lock().unwrap().insert(
+        key,
+        ConnectedPlayer {
+            username: session.username.clone().unwrap_or_default(),
+            stream: client.try_clone()?,
+        },
+    );
+    let result = copy_both_ways(client, backend);
+    connected_players.lock().unwrap().remove(&key);
+    let (sent, received) = result?;
+    info!(
+        "[Passthrough] {} <-> {}: {} bytes sent, {} bytes received in {:.3}s",
+        session.peer_address,
+        address,
+        bytes.len() as u64 + sent,
+        received,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+// returns the number of bytes sent to and received from the backend
+fn copy_both_ways(client: TcpStream, backend: TcpStream) -> Result<(u64, u64)> {
+    let mut client_reader = client.try_clone()?;
+    let mut backend_writer = backend.try_clone()?;
+    let upstream = thread::spawn(move || {
+        let n = copy(&mut client_reader, &mut backend_writer);
+        let _ = backend_writer.shutdown(Shutdown::Write);
+        n
+    });
+
+    let mut backend_reader = backend;
+    let mut client_writer = client;
+    let received = copy(&mut backend_reader, &mut client_writer);
+    // the client may still be connected after the backend has closed
+    let _ = client_writer.shutdown(Shutdown::Both);
+
+    let sent = upstream.join().unwrap_or_default();
+    Ok((sent, received))
+}
+
+// stops at EOF or at the first error, whichever comes first
+fn copy(from: &mut impl Read, to: &mut impl Write) -> u64 {
+    let mut buf = [0; 8192];
+    let mut total: u64 = 0;
+    loop {
+        let n = match from.read(&mut buf) {
+            Ok(0) | Err(_) => return total,
+            Ok(n) => n,
+        };
+        if to.write_all(&buf[..n]).is_err() {
+            return total;
+        }
+        total += n as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::datatype::uuid;
+    use crate::server::{self, Server};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+
+    fn login_packets(port: u16) -> (Vec<u8>, Vec<u8>) {
+        let handshake = packet::encode_packet(
+            0x00,
+            &[
+                &varint::VarInt::from(764),
+                &string::String::from("mc.example.com"),
+                &unsigned_short::UnsignedShort::from(port),
+                &varint::VarInt::from(2),
+            ],
+        );
+        let login_start = packet::encode_packet(
+            0x00,
+            &[
+                &string::String::from("Notch"),
+                &uuid::UUID::from(0x069a79f444e94726a5befca90e38aaf5),
+            ],
+        );
+        (handshake, login_start)
+    }
+
+    fn read_exactly(stream: &mut TcpStream, length: usize) -> Vec<u8> {
+        let mut bytes = vec![0; length];
+        stream.read_exact(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_splice() {
+        let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+        let backend_address = backend.local_addr().unwrap();
+        let fake = TcpListener::bind("127.0.0.1:0").unwrap();
+        let fake_address: SocketAddr = fake.local_addr().unwrap();
+        let (handshake, login_start) = login_packets(fake_address.port());
+
+        let server = Arc::new(Server {
+            passthrough_backend: Some(backend_address.to_string()),
+            ..Default::default()
+        });
+        let fake_thread = thread::spawn(move || {
+            let (mut stream, peer_address) = fake.accept().unwrap();
+            let mut session = Session::new(peer_address, server);
+            server::handle_connection(&mut session, &mut stream).unwrap();
+            assert_eq!(session.username, Some("Notch".to_string()));
+            splice(&session, stream).unwrap();
+        });
+
+        let mut client = TcpStream::connect(fake_address).unwrap();
+        client.write_all(&handshake).unwrap();
+        client.write_all(&login_start).unwrap();
+        client.write_all(b"after login").unwrap();
+
+        // the backend sees the same handshake and login start, then the rest of the stream
+        let (mut stream, _) = backend.accept().unwrap();
+        assert_eq!(read_exactly(&mut stream, handshake.len()), handshake);
+        assert_eq!(read_exactly(&mut stream, login_start.len()), login_start);
+        assert_eq!(read_exactly(&mut stream, 11), b"after login");
+
+        stream.write_all(b"from backend").unwrap();
+        assert_eq!(read_exactly(&mut client, 12), b"from backend");
+
+        // closing the backend ends the session
+        drop(stream);
+        let mut rest = vec![];
+        client.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        fake_thread.join().unwrap();
+    }
+}
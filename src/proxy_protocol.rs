@@ -0,0 +1,269 @@
+use crate::cidr::Cidr;
+use crate::session::Session;
+use log::{debug, warn};
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::{error, fmt};
+
+type Result<T> = std::result::Result<T, ProxyProtocolError>;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LENGTH: usize = 107;
+const V2_SIGNATURE: [u8; 12] = [
+    0x0d, 0x0a, 0x0d, 0x0a, 0x00, 0x0d, 0x0a, 0x51, 0x55, 0x49, 0x54, 0x0a,
+];
+
+// replace the peer address with the one announced by a trusted load balancer
+pub fn accept(session: &mut Session, stream: &mut impl Read) -> Result<()> {
+    if !session.server.proxy_protocol {
+        return Ok(());
+    }
+
+    if !is_trusted(
+        &session.server.proxy_protocol_trusted,
+        &session.peer_address.ip(),
+    ) {
+        debug!(
+            "{} is not a trusted proxy, skipping PROXY header",
+            session.peer_address
+        );
+        return Ok(());
+    }
+
+    if let Some(address) = read_header(stream)? {
+        debug!("PROXY header: {} -> {}", session.peer_address, address);
+        session.peer_address = address;
+    }
+    Ok(())
+}
+
+// an empty list trusts no source, as anyone could claim any address
+fn is_trusted(trusted: &[String], address: &IpAddr) -> bool {
+    trusted.iter().any(|t| match Cidr::from_str(t) {
+        Ok(cidr) => cidr.contains(address),
+        Err(e) => {
+            warn!("{}", e);
+            false
+        }
+    })
+}
+
+// returns None for LOCAL / UNKNOWN headers, which carry no client address
+pub fn read_header(stream: &mut impl Read) -> Result<Option<SocketAddr>> {
+    let mut prefix: [u8; 12] = [0; 12];
+    read_exact(stream, &mut prefix)?;
+
+    if prefix == V2_SIGNATURE {
+        read_v2(stream)
+    } else if prefix.starts_with(V1_PREFIX) {
+        read_v1(stream, &prefix)
+    } else {
+        Err(ProxyProtocolError::InvalidHeader(
+            "Missing PROXY protocol signature".to_string(),
+        ))
+    }
+}
+
+fn read_v1(stream: &mut impl Read, prefix: &[u8]) -> Result<Option<SocketAddr>> {
+    let mut line: Vec<u8> = prefix.to_vec();
+    let mut byte = [0; 1];
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LENGTH {
+            return Err(ProxyProtocolError::InvalidHeader(
+                "PROXY v1 header is too long".to_string(),
+            ));
+        }
+        read_exact(stream, &mut byte)?;
+        line.push(byte[0]);
+    }
+
+    let line = match std::str::from_utf8(&line[..line.len() - 2]) {
+        Ok(l) => l,
+        Err(_) => {
+            return Err(ProxyProtocolError::InvalidHeader(
+                "PROXY v1 header is not ASCII".to_string(),
+            ))
+        }
+    };
+    let fields: Vec<&str> = line.split(' ').collect();
+    match fields[..] {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", "TCP4" | "TCP6", source_address, _, source_port, _] => {
+            let address = IpAddr::from_str(source_address);
+            let port = u16::from_str(source_port);
+            match (address, port) {
+                (Ok(a), Ok(p)) => Ok(Some(SocketAddr::new(a, p))),
+                _ => Err(ProxyProtocolError::InvalidHeader(format!(
+                    "Invalid PROXY v1 address: {}",
+                    line
+                ))),
+            }
+        }
+        _ => Err(ProxyProtocolError::InvalidHeader(format!(
+            "Invalid PROXY v1 header: {}",
+            line
+        ))),
+    }
+}
+
+fn read_v2(stream: &mut impl Read) -> Result<Option<SocketAddr>> {
+    let mut header: [u8; 4] = [0; 4];
+    read_exact(stream, &mut header)?;
+
+    let version = header[0] >> 4;
+    let command = header[0] & 0x0f;
+    let family = header[1] >> 4;
+    let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+    if version != 2 {
+        return Err(ProxyProtocolError::InvalidHeader(format!(
+            "Unsupported PROXY protocol version: {}",
+            version
+        )));
+    }
+
+    // the whole address block (including TLVs) is consumed
+    let mut addresses: Vec<u8> = vec![0; length];
+    read_exact(stream, &mut addresses)?;
+
+    // LOCAL (health checks from the proxy itself)
+    if command == 0x0 {
+        return Ok(None);
+    }
+    if command != 0x1 {
+        return Err(ProxyProtocolError::InvalidHeader(format!(
+            "Unsupported PROXY v2 command: {}",
+            command
+        )));
+    }
+
+    match family {
+        // AF_INET
+        0x1 if length >= 12 => {
+            let address: [u8; 4] = addresses[0..4].try_into().unwrap();
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Ok(Some(SocketAddr::new(Ipv4Addr::from(address).into(), port)))
+        }
+        // AF_INET6
+        0x2 if length >= 36 => {
+            let address: [u8; 16] = addresses[0..16].try_into().unwrap();
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Ok(Some(SocketAddr::new(Ipv6Addr::from(address).into(), port)))
+        }
+        // AF_UNSPEC, AF_UNIX
+        0x0 | 0x3 => Ok(None),
+        _ => Err(ProxyProtocolError::InvalidHeader(format!(
+            "Invalid PROXY v2 address family: {} (length {})",
+            family, length
+        ))),
+    }
+}
+
+fn read_exact(stream: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    if let Err(e) = stream.read_exact(buf) {
+        return Err(ProxyProtocolError::ReadError(e.to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProxyProtocolError {
+    ReadError(String),
+    InvalidHeader(String),
+}
+
+impl fmt::Display for ProxyProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyProtocolError::ReadError(s) => {
+                write!(f, "Could not read PROXY protocol header: {}", s)
+            }
+            ProxyProtocolError::InvalidHeader(s) => write!(f, "PROXY protocol error: {}", s),
+        }
+    }
+}
+
+impl error::Error for ProxyProtocolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_read_v1_tcp4() {
+        let mut bytes: VecDeque<u8> =
+            VecDeque::from(b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 25565\r\n\x10".to_vec());
+        assert_eq!(
+            read_header(&mut bytes),
+            Ok(Some(SocketAddr::from_str("203.0.113.7:51234").unwrap()))
+        );
+        // the handshake must be left untouched
+        assert_eq!(bytes, VecDeque::from([0x10]));
+    }
+
+    #[test]
+    fn test_read_v1_tcp6() {
+        let mut bytes: VecDeque<u8> =
+            VecDeque::from(b"PROXY TCP6 2001:db8::1 2001:db8::2 40000 25565\r\n".to_vec());
+        assert_eq!(
+            read_header(&mut bytes),
+            Ok(Some(SocketAddr::from_str("[2001:db8::1]:40000").unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_read_v1_unknown() {
+        let mut bytes: VecDeque<u8> = VecDeque::from(b"PROXY UNKNOWN\r\n".to_vec());
+        assert_eq!(read_header(&mut bytes), Ok(None));
+    }
+
+    #[test]
+    fn test_read_v1_too_long() {
+        let mut line = b"PROXY TCP4 ".to_vec();
+        line.extend([b'1'; 120]);
+        let mut bytes: VecDeque<u8> = VecDeque::from(line);
+        assert!(read_header(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_read_v2_tcp4() {
+        let mut bytes: VecDeque<u8> = VecDeque::from(V2_SIGNATURE.to_vec());
+        bytes.extend([0x21, 0x11, 0x00, 0x0c]);
+        bytes.extend([203, 0, 113, 7, 10, 0, 0, 1, 0xc8, 0x22, 0x63, 0xdd]);
+        bytes.push_back(0x10);
+        assert_eq!(
+            read_header(&mut bytes),
+            Ok(Some(SocketAddr::from_str("203.0.113.7:51234").unwrap()))
+        );
+        assert_eq!(bytes, VecDeque::from([0x10]));
+    }
+
+    #[test]
+    fn test_read_v2_local() {
+        let mut bytes: VecDeque<u8> = VecDeque::from(V2_SIGNATURE.to_vec());
+        bytes.extend([0x20, 0x00, 0x00, 0x00]);
+        assert_eq!(read_header(&mut bytes), Ok(None));
+    }
+
+    #[test]
+    fn test_read_missing_header() {
+        let mut bytes: VecDeque<u8> = VecDeque::from([0x10; 16]);
+        assert!(matches!(
+            read_header(&mut bytes),
+            Err(ProxyProtocolError::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_trusted() {
+        let trusted = vec!["10.0.0.0/8".to_string(), "::1".to_string()];
+        assert!(is_trusted(&trusted, &IpAddr::from_str("10.1.2.3").unwrap()));
+        assert!(is_trusted(&trusted, &IpAddr::from_str("::1").unwrap()));
+        assert!(!is_trusted(
+            &trusted,
+            &IpAddr::from_str("192.168.0.1").unwrap()
+        ));
+        assert!(!is_trusted(&[], &IpAddr::from_str("192.168.0.1").unwrap()));
+    }
+}
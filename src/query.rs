@@ -0,0 +1,258 @@
+use crate::server::Server;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// https://wiki.vg/Query
+const MAGIC: [u8; 2] = [0xfe, 0xfd];
+const TYPE_HANDSHAKE: u8 = 0x09;
+const TYPE_STAT: u8 = 0x00;
+// the vanilla server issues a new challenge token every 30 seconds
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(30);
+const GAME_TYPE: &str = "SMP";
+const GAME_ID: &str = "MINECRAFT";
+const MAP: &str = "world";
+
+// answers the UDP query protocol (GameSpy4) with the same values as the status response
+#[derive(Debug)]
+pub struct QueryServer {
+    server: Arc<Server>,
+    host_ip: String,
+    host_port: u16,
+    challenges: HashMap<SocketAddr, (i32, Instant)>,
+}
+
+impl QueryServer {
+    // the address of the Minecraft server, not of the query listener
+    pub fn new(server: Arc<Server>, host_ip: &str, host_port: u16) -> QueryServer {
+        QueryServer {
+            server,
+            host_ip: host_ip.to_string(),
+            host_port,
+            challenges: HashMap::new(),
+        }
+    }
+
+    pub fn run(&mut self, socket: &UdpSocket) -> io::Result<()> {
+        let mut buf = [0; 1460];
+        loop {
+            let (n, source) = socket.recv_from(&mut buf)?;
+            if let Some(response) = self.handle(&buf[..n], source) {
+                if let Err(e) = socket.send_to(&response, source) {
+                    warn!("[Query] Could not respond to {}: {}", source, e);
+                }
+            }
+        }
+    }
+
+    // None if the request is to be ignored, as the vanilla server does
+    pub fn handle(&mut self, request: &[u8], source: SocketAddr) -> Option<Vec<u8>> {
+        if request.len() < 7 || request[..2] != MAGIC {
+            debug!("[Query] Invalid request from {}", source);
+            return None;
+        }
+        let request_type = request[2];
+        let session_id = &request[3..7];
+        let payload = &request[7..];
+
+        let mut response = vec![request_type];
+        response.extend_from_slice(session_id);
+        match request_type {
+            TYPE_HANDSHAKE => {
+                let token = self.issue_challenge(source);
+                info!("[Query] {} handshake", source);
+                push_string(&mut response, &token.to_string());
+            }
+            TYPE_STAT if payload.len() >= 4 => {
+                let token = i32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                if !self.check_challenge(source, token) {
+                    debug!("[Query] Invalid challenge token from {}", source);
+                    return None;
+                }
+                // the full stat request is padded to 8 bytes
+                if payload.len() >= 8 {
+                    info!("[Query] {} full stat", source);
+                    self.write_full_stat(&mut response);
+                } else {
+                    info!("[Query] {} basic stat", source);
+                    self.write_basic_stat(&mut response);
+                }
+            }
+            _ => {
+                debug!(
+                    "[Query] Unknown request type from {}: {}",
+                    source, request_type
+                );
+                return None;
+            }
+        }
+        Some(response)
+    }
+
+    fn issue_challenge(&mut self, source: SocketAddr) -> i32 {
+        let now = Instant::now();
+        self.challenges
+            .retain(|_, (_, issued)| now.duration_since(*issued) < CHALLENGE_LIFETIME);
+
+        let mut bytes: [u8; 4] = [0; 4];
+        if openssl::rand::rand_bytes(&mut bytes).is_err() {
+            warn!("Could not generate a challenge token");
+        }
+        // the token is sent back as a decimal string and parsed as a signed int
+        let token = i32::from_be_bytes(bytes) & 0x7fffffff;
+        self.challenges.insert(source, (token, now));
+        token
+    }
+
+    fn check_challenge(&self, source: SocketAddr, token: i32) -> bool {
+        self.challenges
+            .get(&source)
+            .is_some_and(|(t, issued)| *t == token && issued.elapsed() < CHALLENGE_LIFETIME)
+    }
+
+    fn write_basic_stat(&self, response: &mut Vec<u8>) {
+        let profile = &self.server.virtual_hosts.read().unwrap().default;
+        push_string(response, &profile.description);
+        push_string(response, GAME_TYPE);
+        push_string(response, MAP);
+        push_string(response, &profile.players_online.to_string());
+        push_string(response, &profile.players_max.to_string());
+        response.extend_from_slice(&self.host_port.to_le_bytes());
+        push_string(response, &self.host_ip);
+    }
+
+    fn write_full_stat(&self, response: &mut Vec<u8>) {
+        let profile = &self.server.virtual_hosts.read().unwrap().default;
+        response.extend_from_slice(b"splitnum\0\x80\0");
+        let values = [
+            ("hostname", profile.description.clone()),
+            ("gametype", GAME_TYPE.to_string()),
+            ("game_id", GAME_ID.to_string()),
+            ("version", profile.version_name.clone()),
+            ("plugins", String::new()),
+            ("map", MAP.to_string()),
+            ("numplayers", profile.players_online.to_string()),
+            ("maxplayers", profile.players_max.to_string()),
+            ("hostport", self.host_port.to_string()),
+            ("hostip", self.host_ip.clone()),
+        ];
+        for (key, value) in values {
+            push_string(response, key);
+            push_string(response, &value);
+        }
+        response.push(0);
+
+        // the status response does not list any players either
+        response.extend_from_slice(b"\x01player_\0\0");
+        response.push(0);
+    }
+}
+
+// null-terminated, as the clients do not expect anything but ASCII
+fn push_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.push(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "203.0.113.7:54321";
+    const SESSION_ID: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+
+    fn request(request_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut request = MAGIC.to_vec();
+        request.push(request_type);
+        request.extend_from_slice(&SESSION_ID);
+        request.extend_from_slice(payload);
+        request
+    }
+
+    fn handshake(query: &mut QueryServer) -> [u8; 4] {
+        let response = query
+            .handle(&request(TYPE_HANDSHAKE, &[]), SOURCE.parse().unwrap())
+            .unwrap();
+        assert_eq!(response[..5], [TYPE_HANDSHAKE, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(response.last(), Some(&0));
+        let token: i32 = std::str::from_utf8(&response[5..response.len() - 1])
+            .unwrap()
+            .parse()
+            .unwrap();
+        token.to_be_bytes()
+    }
+
+    #[test]
+    fn test_basic_stat() {
+        let mut query = QueryServer::new(Arc::new(Server::default()), "0.0.0.0", 25565);
+        let token = handshake(&mut query);
+
+        let response = query
+            .handle(&request(TYPE_STAT, &token), SOURCE.parse().unwrap())
+            .unwrap();
+        let mut expected = vec![TYPE_STAT, 0x00, 0x00, 0x00, 0x01];
+        expected.extend_from_slice(b"A Minecraft Server\0SMP\0world\x000\x0020\0");
+        expected.extend_from_slice(&[0xdd, 0x63]);
+        expected.extend_from_slice(b"0.0.0.0\0");
+        assert_eq!(response, expected);
+    }
+
+    #[test]
+    fn test_full_stat() {
+        let mut query = QueryServer::new(Arc::new(Server::default()), "0.0.0.0", 25565);
+        let token = handshake(&mut query);
+
+        let mut payload = token.to_vec();
+        payload.extend_from_slice(&[0; 4]);
+        let response = query
+            .handle(&request(TYPE_STAT, &payload), SOURCE.parse().unwrap())
+            .unwrap();
+        let mut expected = vec![TYPE_STAT, 0x00, 0x00, 0x00, 0x01];
+        expected.extend_from_slice(b"splitnum\0\x80\0");
+        for value in [
+            "hostname",
+            "A Minecraft Server",
+            "gametype",
+            "SMP",
+            "game_id",
+            "MINECRAFT",
+            "version",
+            "1.20.2",
+            "plugins",
+            "",
+            "map",
+            "world",
+            "numplayers",
+            "0",
+            "maxplayers",
+            "20",
+            "hostport",
+            "25565",
+            "hostip",
+            "0.0.0.0",
+        ] {
+            push_string(&mut expected, value);
+        }
+        expected.push(0);
+        expected.extend_from_slice(b"\x01player_\0\0\0");
+        assert_eq!(response, expected);
+    }
+
+    #[test]
+    fn test_invalid_challenge() {
+        let mut query = QueryServer::new(Arc::new(Server::default()), "0.0.0.0", 25565);
+        let token = handshake(&mut query);
+
+        // from another address
+        let other: SocketAddr = "203.0.113.8:54321".parse().unwrap();
+        assert_eq!(query.handle(&request(TYPE_STAT, &token), other), None);
+
+        let wrong = (i32::from_be_bytes(token) ^ 1).to_be_bytes();
+        let source = SOURCE.parse().unwrap();
+        assert_eq!(query.handle(&request(TYPE_STAT, &wrong), source), None);
+        assert_eq!(query.handle(&[0xfe, 0xfd, 0x09], source), None);
+    }
+}
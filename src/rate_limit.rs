@@ -0,0 +1,294 @@
+use crate::session::SessionState;
+use config::Config;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct BucketConfig {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    config: BucketConfig,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(config: BucketConfig, now: Instant) -> TokenBucket {
+        TokenBucket {
+            config,
+            tokens: config.burst as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.config.per_second).min(self.config.burst as f64);
+        self.last_refill = now;
+    }
+
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        if self.has_token(now) {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn has_token(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= 1.0
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.config.burst as f64
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Throttle {
+    PerIp,
+    Global,
+}
+
+impl fmt::Display for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Throttle::PerIp => write!(f, "per-IP limit"),
+            Throttle::Global => write!(f, "global limit"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Limit {
+    per_ip_config: Option<BucketConfig>,
+    per_ip: HashMap<IpAddr, TokenBucket>,
+    global: Option<TokenBucket>,
+    allowed: u64,
+    throttled: u64,
+}
+
+impl Limit {
+    const PRUNE_THRESHOLD: usize = 4096;
+
+    fn new(per_ip: Option<BucketConfig>, global: Option<BucketConfig>, now: Instant) -> Limit {
+        Limit {
+            per_ip_config: per_ip,
+            per_ip: HashMap::new(),
+            global: global.map(|c| TokenBucket::new(c, now)),
+            allowed: 0,
+            throttled: 0,
+        }
+    }
+
+    fn check(&mut self, address: IpAddr, now: Instant) -> Result<(), Throttle> {
+        let res = self.take(address, now);
+        match res {
+            Ok(_) => self.allowed += 1,
+            Err(_) => self.throttled += 1,
+        }
+        res
+    }
+
+    // nothing is taken unless both buckets have a token
+    fn take(&mut self, address: IpAddr, now: Instant) -> Result<(), Throttle> {
+        let mut per_ip = None;
+        if let Some(config) = self.per_ip_config {
+            // forget clients whose buckets have refilled completely
+            if self.per_ip.len() >= Self::PRUNE_THRESHOLD {
+                self.per_ip.retain(|_, bucket| !bucket.is_full(now));
+            }
+
+            let bucket = self
+                .per_ip
+                .entry(address)
+                .or_insert_with(|| TokenBucket::new(config, now));
+            if !bucket.has_token(now) {
+                return Err(Throttle::PerIp);
+            }
+            per_ip = Some(bucket);
+        }
+
+        if let Some(global) = &mut self.global {
+            if !global.try_take(now) {
+                return Err(Throttle::Global);
+            }
+        }
+        if let Some(bucket) = per_ip {
+            bucket.try_take(now);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    connection: Limit,
+    status: Limit,
+    login: Limit,
+}
+
+impl RateLimiter {
+    pub fn new(
+        connection_per_ip: Option<BucketConfig>,
+        connection_global: Option<BucketConfig>,
+        status_per_ip: Option<BucketConfig>,
+        status_global: Option<BucketConfig>,
+        login_per_ip: Option<BucketConfig>,
+        login_global: Option<BucketConfig>,
+    ) -> RateLimiter {
+        let now = Instant::now();
+        RateLimiter {
+            connection: Limit::new(connection_per_ip, connection_global, now),
+            status: Limit::new(status_per_ip, status_global, now),
+            login: Limit::new(login_per_ip, login_global, now),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> RateLimiter {
+        let get = |key: &str| {
+            config
+                .get::<BucketConfig>(&format!("rate_limit.{}", key))
+                .ok()
+        };
+        RateLimiter::new(
+            get("connection_per_ip"),
+            get("connection_global"),
+            get("status_per_ip"),
+            get("status_global"),
+            get("login_per_ip"),
+            get("login_global"),
+        )
+    }
+
+    // every accepted connection, before anything is read from it
+    pub fn check_connection(&mut self, address: IpAddr, now: Instant) -> Result<(), Throttle> {
+        self.connection.check(address, now)
+    }
+
+    pub fn check(
+        &mut self,
+        state: &SessionState,
+        address: IpAddr,
+        now: Instant,
+    ) -> Result<(), Throttle> {
+        match state {
+            SessionState::STATUS => self.status.check(address, now),
+            SessionState::LOGIN => self.login.check(address, now),
+            SessionState::HANDSHAKING => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "connection: {} allowed / {} throttled, status: {} allowed / {} throttled, login: {} allowed / {} throttled",
+            self.connection.allowed,
+            self.connection.throttled,
+            self.status.allowed,
+            self.status.throttled,
+            self.login.allowed,
+            self.login.throttled
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    const BUCKET: BucketConfig = BucketConfig {
+        burst: 2,
+        per_second: 1.0,
+    };
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(BUCKET, now);
+        assert!(bucket.try_take(now));
+        assert!(bucket.try_take(now));
+        assert!(!bucket.try_take(now));
+        assert!(!bucket.try_take(now + Duration::from_millis(500)));
+        assert!(bucket.try_take(now + Duration::from_millis(1500)));
+        // never refills beyond the burst size
+        assert!(bucket.try_take(now + Duration::from_secs(60)));
+        assert!(bucket.try_take(now + Duration::from_secs(60)));
+        assert!(!bucket.try_take(now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_rate_limiter_per_ip() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(None, None, None, None, Some(BUCKET), None);
+        let a = IpAddr::from_str("192.0.2.1").unwrap();
+        let b = IpAddr::from_str("192.0.2.2").unwrap();
+
+        assert_eq!(limiter.check(&SessionState::LOGIN, a, now), Ok(()));
+        assert_eq!(limiter.check(&SessionState::LOGIN, a, now), Ok(()));
+        assert_eq!(
+            limiter.check(&SessionState::LOGIN, a, now),
+            Err(Throttle::PerIp)
+        );
+        assert_eq!(limiter.check(&SessionState::LOGIN, b, now), Ok(()));
+        // status pings are limited separately
+        assert_eq!(limiter.check(&SessionState::STATUS, a, now), Ok(()));
+        assert_eq!(
+            limiter.to_string(),
+            "connection: 0 allowed / 0 throttled, status: 1 allowed / 0 throttled, login: 3 allowed / 1 throttled"
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_global() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(None, None, None, Some(BUCKET), None, None);
+        for i in 0..2 {
+            let address = IpAddr::from_str(&format!("192.0.2.{}", i)).unwrap();
+            assert_eq!(limiter.check(&SessionState::STATUS, address, now), Ok(()));
+        }
+        assert_eq!(
+            limiter.check(
+                &SessionState::STATUS,
+                IpAddr::from_str("192.0.2.9").unwrap(),
+                now
+            ),
+            Err(Throttle::Global)
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_global_does_not_charge_per_ip() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(None, None, None, None, Some(BUCKET), Some(BUCKET));
+        let a = IpAddr::from_str("192.0.2.1").unwrap();
+        let b = IpAddr::from_str("192.0.2.2").unwrap();
+
+        assert_eq!(limiter.check(&SessionState::LOGIN, a, now), Ok(()));
+        assert_eq!(limiter.check(&SessionState::LOGIN, b, now), Ok(()));
+        assert_eq!(
+            limiter.check(&SessionState::LOGIN, a, now),
+            Err(Throttle::Global)
+        );
+        // the rejected attempt left a's own token in place
+        assert_eq!(limiter.login.per_ip[&a].tokens, 1.0);
+    }
+}
@@ -0,0 +1,402 @@
+use crate::packet::{PacketError, Result};
+use crate::server::Server;
+use config::Config;
+use log::{info, warn};
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+// https://wiki.vg/RCON
+const TYPE_RESPONSE: i32 = 0;
+const TYPE_COMMAND: i32 = 2;
+const TYPE_AUTH_RESPONSE: i32 = 2;
+const TYPE_LOGIN: i32 = 3;
+const AUTH_FAILURE_ID: i32 = -1;
+// request id, type and the two null bytes
+const MIN_LENGTH: i32 = 10;
+const MAX_REQUEST_LENGTH: i32 = 1460;
+// longer responses are split into several packets
+const MAX_RESPONSE_LENGTH: usize = 4096;
+const BAN_SOURCE: &str = "Rcon";
+
+#[derive(Debug, PartialEq)]
+struct RconPacket {
+    request_id: i32,
+    packet_type: i32,
+    payload: String,
+}
+
+#[derive(Debug)]
+pub struct RconServer {
+    server: Arc<Server>,
+    password: String,
+    // used by the reload command
+    load_config: fn() -> Config,
+}
+
+impl RconServer {
+    pub fn new(server: Arc<Server>, password: &str, load_config: fn() -> Config) -> RconServer {
+        RconServer {
+            server,
+            password: password.to_string(),
+            load_config,
+        }
+    }
+
+    // reads packets until the connection is closed or the login fails
+    pub fn handle_connection(
+        &self,
+        stream: &mut (impl Read + Write),
+        peer_address: SocketAddr,
+    ) -> Result<()> {
+        let mut is_authenticated = false;
+        loop {
+            let request = match read_packet(stream, MAX_REQUEST_LENGTH) {
+                Ok(r) => r,
+                Err(e) if is_eof(e.as_ref()) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            match request.packet_type {
+                TYPE_LOGIN => {
+                    is_authenticated = self.check_password(&request.payload);
+                    info!(
+                        "[RCON] Login from {}: {}",
+                        peer_address,
+                        if is_authenticated {
+                            "success"
+                        } else {
+                            "failure"
+                        }
+                    );
+                    if !is_authenticated {
+                        write_packet(stream, AUTH_FAILURE_ID, TYPE_AUTH_RESPONSE, "")?;
+                        return Ok(());
+                    }
+                    write_packet(stream, request.request_id, TYPE_AUTH_RESPONSE, "")?;
+                }
+                _ if !is_authenticated => {
+                    warn!("[RCON] {} sent a command before logging in", peer_address);
+                    write_packet(stream, AUTH_FAILURE_ID, TYPE_AUTH_RESPONSE, "")?;
+                    return Ok(());
+                }
+                TYPE_COMMAND => {
+                    info!("[RCON] {}: {}", peer_address, request.payload);
+                    let response = self.execute(&request.payload);
+                    write_response(stream, request.request_id, &response)?;
+                }
+                packet_type => {
+                    let response = format!("Unknown request {:x}", packet_type);
+                    write_response(stream, request.request_id, &response)?;
+                }
+            }
+        }
+    }
+
+    fn check_password(&self, password: &str) -> bool {
+        !self.password.is_empty()
+            && password.len() == self.password.len()
+            && openssl::memcmp::eq(password.as_bytes(), self.password.as_bytes())
+    }
+
+    pub fn execute(&self, command: &str) -> String {
+        let command = command.trim().trim_start_matches('/');
+        let (name, arguments) = command.split_once(' ').unwrap_or((command, ""));
+        let arguments = arguments.trim();
+
+        match (name, arguments) {
+            ("list", _) => {
+                let profile = &self.server.virtual_hosts.read().unwrap().default;
+                let mut names: Vec<String> = self
+                    .server
+                    .connected_players
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .map(|player| player.username.clone())
+                    .collect();
+                names.sort();
+                format!(
+                    "There are {} of a max of {} players online: {}",
+                    profile.players_online,
+                    profile.players_max,
+                    names.join(", ")
+                )
+            }
+            ("say", message) if !message.is_empty() => {
+                info!("[RCON] [Server] {}", message);
+                String::new()
+            }
+            ("kick", arguments) if !arguments.is_empty() => {
+                let username = arguments.split(' ').next().unwrap_or_default();
+                if self.server.kick(username) {
+                    format!("Kicked {}", username)
+                } else {
+                    "No player was found".to_string()
+                }
+            }
+            ("ban", target) if !target.is_empty() && !target.contains(' ') => {
+                let mut ban_list = self.server.ban_list.write().unwrap();
+                // anything that is not an address is a player name
+                if ban_list.ban_ip(target, BAN_SOURCE).is_ok() {
+                    format!("Banned IP {}", target)
+                } else {
+                    ban_list.ban_player(target, BAN_SOURCE);
+                    drop(ban_list);
+                    self.server.kick(target);
+                    format!("Banned {}", target)
+                }
+            }
+            ("motd", text) if !text.is_empty() => {
+                self.server
+                    .virtual_hosts
+                    .write()
+                    .unwrap()
+                    .set_description(text);
+                format!("Set the MOTD to {}", text)
+            }
+            ("players", arguments) => {
+                let counts: Vec<u16> = arguments
+                    .split(' ')
+                    .filter_map(|a| a.parse().ok())
+                    .collect();
+                let &[online, max] = &counts[..] else {
+                    return "Usage: players <online> <max>".to_string();
+                };
+                self.server
+                    .virtual_hosts
+                    .write()
+                    .unwrap()
+                    .set_players(online, max);
+                format!("Set the player count to {}/{}", online, max)
+            }
+            ("reload", _) => {
+                self.server.reload(&(self.load_config)());
+                "Reloaded the configuration".to_string()
+            }
+            ("say" | "kick" | "ban" | "motd", _) => format!("Usage: {} <{}>", name, usage(name)),
+            _ => format!("Unknown command: {}", name),
+        }
+    }
+}
+
+fn usage(command: &str) -> &'static str {
+    match command {
+        "say" => "message",
+        "kick" => "name",
+        "ban" => "ip|name",
+        _ => "text",
+    }
+}
+
+fn is_eof(e: &(dyn std::error::Error + 'static)) -> bool {
+    e.downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+}
+
+// all integers are little-endian
+fn read_packet(stream: &mut impl Read, max_length: i32) -> Result<RconPacket> {
+    let mut int = [0; 4];
+    stream.read_exact(&mut int)?;
+    let length = i32::from_le_bytes(int);
+    if !(MIN_LENGTH..=max_length).contains(&length) {
+        return Err(
+            PacketError::ReadError(format!("Invalid RCON packet length: {}", length)).into(),
+        );
+    }
+
+    let mut body = vec![0; length as usize];
+    stream.read_exact(&mut body)?;
+    let request_id = i32::from_le_bytes([body[0], body[1], body[2], body[3]]);
+    let packet_type = i32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+    let payload = &body[8..];
+    let end = payload
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(payload.len());
+
+    Ok(RconPacket {
+        request_id,
+        packet_type,
+        payload: String::from_utf8_lossy(&payload[..end]).to_string(),
+    })
+}
+
+fn write_packet(
+    stream: &mut impl Write,
+    request_id: i32,
+    packet_type: i32,
+    payload: &str,
+) -> Result<()> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(payload.len() + 14);
+    bytes.extend_from_slice(&(payload.len() as i32 + MIN_LENGTH).to_le_bytes());
+    bytes.extend_from_slice(&request_id.to_le_bytes());
+    bytes.extend_from_slice(&packet_type.to_le_bytes());
+    bytes.extend_from_slice(payload.as_bytes());
+    bytes.extend_from_slice(&[0, 0]);
+    stream.write_all(&bytes)?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn write_response(stream: &mut impl Write, request_id: i32, response: &str) -> Result<()> {
+    if response.is_empty() {
+        return write_packet(stream, request_id, TYPE_RESPONSE, "");
+    }
+    // split between characters, so that every fragment is valid UTF-8
+    let mut rest = response;
+    while !rest.is_empty() {
+        let mut end = rest.len().min(MAX_RESPONSE_LENGTH);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, remaining) = rest.split_at(end);
+        write_packet(stream, request_id, TYPE_RESPONSE, chunk)?;
+        rest = remaining;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ban_list::BanList;
+    use crate::capture::MemoryStream;
+    use std::collections::VecDeque;
+    use std::net::IpAddr;
+
+    const PASSWORD: &str = "hunter2";
+
+    fn rcon() -> RconServer {
+        RconServer::new(Arc::new(Server::default()), PASSWORD, Config::default)
+    }
+
+    fn request(request_id: i32, packet_type: i32, payload: &str) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_packet(&mut bytes, request_id, packet_type, payload).unwrap();
+        bytes
+    }
+
+    fn responses(output: &[u8]) -> Vec<RconPacket> {
+        let mut output = VecDeque::from(output.to_vec());
+        let mut packets = vec![];
+        while !output.is_empty() {
+            let max_length = MAX_RESPONSE_LENGTH as i32 + MIN_LENGTH;
+            packets.push(read_packet(&mut output, max_length).unwrap());
+        }
+        packets
+    }
+
+    fn run(rcon: &RconServer, input: Vec<u8>) -> Vec<RconPacket> {
+        let mut stream = MemoryStream {
+            input: VecDeque::from(input),
+            output: vec![],
+        };
+        rcon.handle_connection(&mut stream, "127.0.0.1:12345".parse().unwrap())
+            .unwrap();
+        responses(&stream.output)
+    }
+
+    #[test]
+    fn test_login_and_command() {
+        let mut input = request(1, TYPE_LOGIN, PASSWORD);
+        input.extend(request(2, TYPE_COMMAND, "list"));
+        assert_eq!(
+            run(&rcon(), input),
+            [
+                RconPacket {
+                    request_id: 1,
+                    packet_type: TYPE_AUTH_RESPONSE,
+                    payload: String::new()
+                },
+                RconPacket {
+                    request_id: 2,
+                    packet_type: TYPE_RESPONSE,
+                    payload: "There are 0 of a max of 20 players online: ".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_login_failure() {
+        // the command is not executed
+        let mut input = request(1, TYPE_LOGIN, "hunter3");
+        input.extend(request(2, TYPE_COMMAND, "list"));
+        let responses = run(&rcon(), input);
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].request_id, AUTH_FAILURE_ID);
+
+        let responses = run(&rcon(), request(2, TYPE_COMMAND, "list"));
+        assert_eq!(responses[0].request_id, AUTH_FAILURE_ID);
+
+        // no password disables the login
+        let rcon = RconServer::new(Arc::new(Server::default()), "", Config::default);
+        let responses = run(&rcon, request(1, TYPE_LOGIN, ""));
+        assert_eq!(responses[0].request_id, AUTH_FAILURE_ID);
+    }
+
+    #[test]
+    fn test_execute() {
+        let rcon = rcon();
+        assert_eq!(
+            rcon.execute("motd Maintenance"),
+            "Set the MOTD to Maintenance"
+        );
+        assert_eq!(rcon.execute("players 5 10"), "Set the player count to 5/10");
+        assert_eq!(
+            rcon.execute("/list"),
+            "There are 5 of a max of 10 players online: "
+        );
+        let profile = rcon.server.virtual_hosts.read().unwrap().resolve("a");
+        assert_eq!(profile.description, "Maintenance");
+
+        assert_eq!(rcon.execute("players 5"), "Usage: players <online> <max>");
+        assert_eq!(rcon.execute("say hello"), "");
+        assert_eq!(rcon.execute("kick Notch"), "No player was found");
+        assert_eq!(rcon.execute("kick"), "Usage: kick <name>");
+        assert_eq!(rcon.execute("op Notch"), "Unknown command: op");
+
+        // the configuration is read again
+        assert_eq!(rcon.execute("reload"), "Reloaded the configuration");
+        let profile = rcon.server.virtual_hosts.read().unwrap().resolve("a");
+        assert_eq!(profile.description, "A Minecraft Server");
+    }
+
+    #[test]
+    fn test_ban() {
+        let rcon = rcon();
+        // not written to the files
+        *rcon.server.ban_list.write().unwrap() = BanList::default();
+        let address: IpAddr = "203.0.113.7".parse().unwrap();
+        assert_eq!(
+            rcon.execute("ban 203.0.113.0/24"),
+            "Banned IP 203.0.113.0/24"
+        );
+        assert_eq!(rcon.execute("ban Notch"), "Banned Notch");
+        assert_eq!(rcon.execute("ban"), "Usage: ban <ip|name>");
+
+        let ban_list = rcon.server.ban_list.read().unwrap();
+        assert!(ban_list.check_ip(&address).is_some());
+        assert!(ban_list.check_player("Notch", None).is_some());
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut output = vec![];
+        write_response(&mut output, 7, &"a".repeat(MAX_RESPONSE_LENGTH + 1)).unwrap();
+        let fragments = responses(&output);
+        assert_eq!(fragments.len(), 2);
+        assert_eq!(fragments[0].payload.len(), MAX_RESPONSE_LENGTH);
+        assert_eq!(fragments[1].payload, "a");
+        assert!(fragments.iter().all(|r| r.request_id == 7));
+
+        // a character is never split across fragments
+        let mut output = vec![];
+        let response = format!("{}é", "a".repeat(MAX_RESPONSE_LENGTH - 1));
+        write_response(&mut output, 7, &response).unwrap();
+        let fragments = responses(&output);
+        assert_eq!(fragments[0].payload.len(), MAX_RESPONSE_LENGTH - 1);
+        assert_eq!(fragments[1].payload, "é");
+    }
+}
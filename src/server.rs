@@ -0,0 +1,358 @@
+use crate::ban_list::BanList;
+use crate::capture::Direction;
+use crate::fingerprint::Fingerprinter;
+use crate::forwarding::{ForwardingError, ForwardingMode};
+use crate::geoip::{GeoIp, Location};
+use crate::metrics::Metrics;
+use crate::packet::s2c_disconnect::S2CDisconnectPacket;
+use crate::packet::{self, ClientBoundPacketBody, RecordingReader, Result, ServerBoundPacketBody};
+use crate::rate_limit::RateLimiter;
+use crate::session::{Session, SessionState};
+use crate::virtual_host::VirtualHosts;
+use config::Config;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
+
+const THROTTLED_REASON: &str = "Connection throttled! Please wait before reconnecting.";
+
+#[derive(Debug)]
+pub struct ConnectedPlayer {
+    pub username: String,
+    pub stream: TcpStream,
+}
+
+// the configuration and the state shared by every session
+#[derive(Debug)]
+pub struct Server {
+    pub virtual_hosts: RwLock<VirtualHosts>,
+    pub forwarding_mode: ForwardingMode,
+    pub forwarding_secret: Option<String>,
+    pub proxy_protocol: bool,
+    pub proxy_protocol_trusted: Vec<String>,
+    pub ban_list: RwLock<BanList>,
+    pub rate_limiter: Mutex<RateLimiter>,
+    pub passthrough_backend: Option<String>,
+    pub capture_dir: Option<String>,
+    pub fingerprinter: Fingerprinter,
+    pub geoip: GeoIp,
+    pub metrics: Mutex<Metrics>,
+    pub metrics_file: Option<String>,
+    // players spliced to the passthrough backend, by the address of their connection,
+    // as several sessions may use the same name in offline mode
+    pub connected_players: Mutex<HashMap<SocketAddr, ConnectedPlayer>>,
+}
+
+impl Server {
+    pub fn from_config(config: &Config) -> Server {
+        Server {
+            virtual_hosts: RwLock::new(VirtualHosts::from_config(config)),
+            forwarding_mode: ForwardingMode::from_config(config),
+            forwarding_secret: config
+                .get::<String>("forwarding_secret")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            proxy_protocol: config.get::<bool>("proxy_protocol").unwrap_or(false),
+            proxy_protocol_trusted: config
+                .get::<Vec<String>>("proxy_protocol_trusted")
+                .unwrap_or_default(),
+            ban_list: RwLock::new(BanList::from_config(config)),
+            rate_limiter: Mutex::new(RateLimiter::from_config(config)),
+            passthrough_backend: config.get::<String>("passthrough_backend").ok(),
+            capture_dir: config.get::<String>("capture_dir").ok(),
+            fingerprinter: Fingerprinter::from_config(config),
+            geoip: GeoIp::from_config(config),
+            metrics: Mutex::new(Metrics::default()),
+            metrics_file: config.get::<String>("metrics_file").ok(),
+            connected_players: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // only the status and the ban lists can be changed without a restart
+    pub fn reload(&self, config: &Config) {
+        *self.virtual_hosts.write().unwrap() = VirtualHosts::from_config(config);
+        *self.ban_list.write().unwrap() = BanList::from_config(config);
+    }
+
+    pub fn record_connection(&self, location: &Location) {
+        self.metrics.lock().unwrap().record_connection(location);
+    }
+
+    // never writes the file itself, so that UDP pings cannot make the server write to disk
+    pub fn record_bedrock_ping(&self) {
+        self.metrics.lock().unwrap().record_bedrock_ping();
+    }
+
+    // only when something has been recorded since, and outside the lock
+    pub fn write_metrics(&self) {
+        let Some(metrics_file) = &self.metrics_file else {
+            return;
+        };
+        let mut metrics = self.metrics.lock().unwrap();
+        if !metrics.changed {
+            return;
+        }
+        metrics.changed = false;
+        let snapshot = metrics.clone();
+        drop(metrics);
+        if let Err(e) = snapshot.write(metrics_file) {
+            warn!("Could not write {}: {}", metrics_file, e);
+        }
+    }
+
+    // kicks every session with the name, returns whether there was any
+    pub fn kick(&self, username: &str) -> bool {
+        let mut connected_players = self.connected_players.lock().unwrap();
+        let count = connected_players.len();
+        connected_players.retain(|_, player| {
+            if !player.username.eq_ignore_ascii_case(username) {
+                return true;
+            }
+            let _ = player.stream.shutdown(Shutdown::Both);
+            false
+        });
+        connected_players.len() < count
+    }
+
+    pub fn velocity_secret(&self) -> std::result::Result<&str, ForwardingError> {
+        self.forwarding_secret.as_deref().ok_or_else(|| {
+            ForwardingError("Velocity forwarding secret is not configured".to_string())
+        })
+    }
+}
+
+// every setting at its default value
+impl Default for Server {
+    fn default() -> Self {
+        Server::from_config(&Config::default())
+    }
+}
+
+// reads packets and responds to them until the session is terminated
+pub fn handle_connection(session: &mut Session, stream: &mut (impl Read + Write)) -> Result<()> {
+    loop {
+        let mut recorder = RecordingReader {
+            inner: stream,
+            bytes: vec![],
+        };
+        let result = read_packet(session, &mut recorder);
+        // nothing more was sent: the client closed the connection between two packets
+        if result.is_err() && recorder.bytes.is_empty() {
+            debug!("Connection closed by {}", session.peer_address);
+            break;
+        }
+        // recorded in the state the packet was read in, even if it could not be parsed
+        if !recorder.bytes.is_empty() {
+            session.record(Direction::Inbound, &recorder.bytes);
+        }
+        let body =
+            result.inspect_err(|e| packet::kick_on_read_error(session, stream, e.as_ref()))?;
+
+        let handshaking = matches!(session.state, SessionState::HANDSHAKING);
+        body.update_session(session);
+        // checked as soon as the next state is known, before any RSA key is generated
+        if handshaking && !check_rate_limit(session, stream)? {
+            break;
+        }
+        body.respond(session, stream)?;
+
+        // terminate
+        if session.next_packet_ids.is_empty() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// throttled status pings are dropped without an answer, and logins get a cheap disconnect
+fn check_rate_limit(session: &mut Session, stream: &mut impl Write) -> Result<bool> {
+    let mut rate_limiter = session.server.rate_limiter.lock().unwrap();
+    let Err(throttle) =
+        rate_limiter.check(&session.state, session.peer_address.ip(), Instant::now())
+    else {
+        return Ok(true);
+    };
+    // not an error, so that a flood does not flood the log as well
+    debug!(
+        "Throttled {} ({}, {}); {}",
+        session.peer_address, session.state, throttle, rate_limiter
+    );
+    drop(rate_limiter);
+
+    session.throttled = true;
+    if let SessionState::LOGIN = session.state {
+        S2CDisconnectPacket::with_reason(THROTTLED_REASON.into())
+            .write_to_stream(session, stream)?;
+    }
+    Ok(false)
+}
+
+fn read_packet(
+    session: &mut Session,
+    stream: &mut impl Read,
+) -> Result<Box<dyn ServerBoundPacketBody>> {
+    let header = packet::read_packet_header_from_stream(session, stream)?;
+    debug!("PacketHeader: {{{}}}", header);
+
+    let body = packet::read_packet_body_from_stream(session, stream, &header)?;
+    debug!("PacketBody received");
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ban_list::IpBanEntry;
+    use crate::capture::MemoryStream;
+    use crate::packet::datatype::{string, unsigned_short, uuid, varint};
+    use crate::rate_limit::BucketConfig;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_kick() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = Server::default();
+        // the same name twice, as allowed in offline mode
+        let mut clients: Vec<TcpStream> = vec![];
+        for username in ["Notch", "notch", "jeb_"] {
+            clients.push(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+            let (stream, peer_address) = listener.accept().unwrap();
+            server.connected_players.lock().unwrap().insert(
+                peer_address,
+                ConnectedPlayer {
+                    username: username.to_string(),
+                    stream,
+                },
+            );
+        }
+
+        assert!(server.kick("NOTCH"));
+        assert!(!server.kick("Notch"));
+        let connected_players = server.connected_players.lock().unwrap();
+        assert_eq!(connected_players.len(), 1);
+        assert!(connected_players.values().all(|p| p.username == "jeb_"));
+        // the kicked clients see the connection closed
+        let mut rest = vec![];
+        clients[0].read_to_end(&mut rest).unwrap();
+        clients[1].read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_write_metrics() {
+        let path = std::env::temp_dir().join("fake-minecraft-server-metrics.json");
+        let _ = std::fs::remove_file(&path);
+        let server = Server {
+            metrics_file: Some(path.to_string_lossy().to_string()),
+            ..Server::default()
+        };
+        // nothing recorded yet
+        server.write_metrics();
+        assert!(!path.exists());
+
+        server.record_connection(&Location::default());
+        server.record_connection(&Location::default());
+        server.write_metrics();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("\"connections\": 2"));
+
+        // not rewritten until something new is recorded
+        std::fs::remove_file(&path).unwrap();
+        server.write_metrics();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_handle_connection_status() {
+        let server = Arc::new(Server::default());
+        let mut session = Session::new("127.0.0.1:12345".parse().unwrap(), server);
+        // Handshake (765, "a", 25565, status), Status Request, Ping Request
+        let mut stream = MemoryStream {
+            input: VecDeque::from([
+                0x08, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x01, 0x01, 0x00, 0x09, 0x01, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a,
+            ]),
+            output: vec![],
+        };
+
+        handle_connection(&mut session, &mut stream).unwrap();
+        assert!(stream.input.is_empty());
+        assert_eq!(session.protocol_version, Some(765));
+
+        // Status Response followed by the Ping Response echoing the payload
+        let ping_response = [0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a];
+        assert!(stream.output.ends_with(&ping_response));
+        let status = String::from_utf8_lossy(&stream.output[..stream.output.len() - 10]);
+        assert!(status.contains("\"protocol\":764"));
+    }
+
+    #[test]
+    fn test_handle_connection_throttled() {
+        let mut server = Server::default();
+        let closed = BucketConfig {
+            burst: 0,
+            per_second: 0.0,
+        };
+        server.rate_limiter =
+            Mutex::new(RateLimiter::new(None, None, None, None, Some(closed), None));
+        let mut session = Session::new("127.0.0.1:12345".parse().unwrap(), Arc::new(server));
+        // Handshake (765, "a", 25565, login), followed by a Login Start which is never read
+        let mut stream = MemoryStream {
+            input: VecDeque::from([
+                0x08, 0x00, 0xfd, 0x05, 0x01, 0x61, 0x63, 0xdd, 0x02, 0x02, 0x00, 0x00,
+            ]),
+            output: vec![],
+        };
+
+        handle_connection(&mut session, &mut stream).unwrap();
+        assert!(session.throttled);
+        assert_eq!(stream.input.len(), 3);
+        assert!(String::from_utf8_lossy(&stream.output).contains(THROTTLED_REASON));
+    }
+
+    #[test]
+    fn test_handle_connection_banned_forwarded_address() {
+        let mut server = Server {
+            forwarding_mode: ForwardingMode::BungeeCord,
+            ..Server::default()
+        };
+        let entry = IpBanEntry {
+            ip: "203.0.113.7".to_string(),
+            created: None,
+            source: None,
+            expires: None,
+            reason: Some("Spam".to_string()),
+        };
+        server.ban_list = RwLock::new(BanList::new(vec![entry], vec![], None));
+        // connected from BungeeCord, which is not banned itself
+        let mut session = Session::new("127.0.0.1:12345".parse().unwrap(), Arc::new(server));
+
+        let uuid: u128 = 0x069a79f444e94726a5befca90e38aaf5;
+        let mut input = packet::encode_packet(
+            0x00,
+            &[
+                &varint::VarInt::from(765),
+                &string::String::from(format!("a\0203.0.113.7\0{:032x}", uuid)),
+                &unsigned_short::UnsignedShort::from(25565),
+                &varint::VarInt::from(2),
+            ],
+        );
+        input.extend(packet::encode_packet(
+            0x00,
+            &[&string::String::from("Notch"), &uuid::UUID::from(uuid)],
+        ));
+        let mut stream = MemoryStream {
+            input: VecDeque::from(input),
+            output: vec![],
+        };
+
+        handle_connection(&mut session, &mut stream).unwrap();
+        assert_eq!(session.peer_address.to_string(), "203.0.113.7:12345");
+        assert!(String::from_utf8_lossy(&stream.output)
+            .contains("Your IP address is banned from this server.\\nReason: Spam"));
+    }
+}
@@ -1,8 +1,16 @@
-use fake_minecraft_server::encryption;
+use crate::capture::{Capture, Direction};
+use crate::encryption;
+use crate::forwarding::ProfileProperty;
+use crate::geoip::Location;
+use crate::server::Server;
+use crate::virtual_host::Profile;
+use log::info;
 use std::fmt;
-use std::net::{SocketAddr, TcpStream};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum SessionState {
     HANDSHAKING,
     STATUS,
@@ -31,18 +39,33 @@ pub struct Session {
     pub server_port: Option<u16>,
     pub username: Option<String>,
     pub uuid: Option<u128>,
+    pub properties: Vec<ProfileProperty>,
     pub is_authenticated: bool,
     pub rsa: Option<encryption::Rsa>,
     pub verify_token: Option<Vec<u8>>,
     pub shared_secret: Option<Vec<u8>>,
+    pub plugin_message_id: Option<i32>,
+    // dropped by the rate limiter, so the hook is not run
+    pub throttled: bool,
+    pub profile: Profile,
+    pub server: Arc<Server>,
+    // set when the connection is to be spliced to the passthrough backend
+    pub passthrough_login_start: Option<Vec<u8>>,
+    pub capture: Option<Mutex<Capture>>,
+    pub status_requested: bool,
+    pub pinged: bool,
+    // classified by the fingerprint rules once the session has ended
+    pub client_kind: Option<String>,
+    pub location: Location,
 }
 
 impl Session {
     pub const FIRST_PACKET_IDS: [i32; 1] = [0x00]; // Handshake
 
-    pub fn new(stream: &TcpStream) -> Session {
+    pub fn new(peer_address: SocketAddr, server: Arc<Server>) -> Session {
+        let profile = server.virtual_hosts.read().unwrap().default.clone();
         Session {
-            peer_address: stream.peer_addr().unwrap(),
+            peer_address,
             state: SessionState::HANDSHAKING,
             next_packet_ids: &Session::FIRST_PACKET_IDS,
             protocol_version: None,
@@ -50,10 +73,38 @@ impl Session {
             server_port: None,
             username: None,
             uuid: None,
+            properties: vec![],
             is_authenticated: false,
             rsa: None,
             verify_token: None,
             shared_secret: None,
+            plugin_message_id: None,
+            throttled: false,
+            profile,
+            server,
+            passthrough_login_start: None,
+            capture: None,
+            status_requested: false,
+            pinged: false,
+            client_kind: None,
+            location: Location::default(),
+        }
+    }
+
+    // looked up again whenever forwarding replaces the address
+    pub fn locate(&mut self) {
+        if self.server.geoip.is_enabled() {
+            self.location = self.server.geoip.lookup(&self.peer_address.ip());
+            info!("[GeoIP] {}: {}", self.peer_address, self.location);
+        }
+    }
+
+    // does nothing unless the session is being captured
+    pub fn record(&self, direction: Direction, payload: &[u8]) {
+        if let Some(capture) = &self.capture {
+            if let Ok(mut capture) = capture.lock() {
+                capture.record(direction, &self.state, payload);
+            }
         }
     }
 }
@@ -0,0 +1,173 @@
+use crate::session::Session;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Templates {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Templates {
+    // one of the templates at random
+    pub fn choose(&self) -> &str {
+        match self {
+            Templates::One(t) => t,
+            Templates::Many(ts) if ts.is_empty() => "",
+            Templates::Many(ts) => {
+                let mut bytes: [u8; 4] = [0; 4];
+                let index = match openssl::rand::rand_bytes(&mut bytes) {
+                    Ok(_) => u32::from_be_bytes(bytes) as usize % ts.len(),
+                    Err(_) => 0,
+                };
+                &ts[index]
+            }
+        }
+    }
+}
+
+impl From<&str> for Templates {
+    fn from(v: &str) -> Self {
+        Templates::One(v.to_string())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AuthResult {
+    Skipped,
+    Success,
+    Failure,
+}
+
+impl AuthResult {
+    // authentication is attempted only once the shared secret has been exchanged
+    pub fn of(session: &Session) -> AuthResult {
+        match (&session.shared_secret, session.is_authenticated) {
+            (None, _) => AuthResult::Skipped,
+            (Some(_), true) => AuthResult::Success,
+            (Some(_), false) => AuthResult::Failure,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthResult::Skipped => "skipped",
+            AuthResult::Success => "success",
+            AuthResult::Failure => "failure",
+        }
+    }
+}
+
+pub fn format_uuid(uuid: u128) -> String {
+    let hex = format!("{:032x}", uuid);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+pub fn variables(session: &Session) -> Vec<(&'static str, String)> {
+    vec![
+        ("username", session.username.clone().unwrap_or_default()),
+        ("uuid", session.uuid.map(format_uuid).unwrap_or_default()),
+        ("ip", session.peer_address.ip().to_string()),
+        (
+            "protocol_version",
+            session
+                .protocol_version
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "server_address",
+            session.server_address.clone().unwrap_or_default(),
+        ),
+        ("auth_result", AuthResult::of(session).as_str().to_string()),
+        (
+            "country",
+            session.location.country.clone().unwrap_or_default(),
+        ),
+        (
+            "asn",
+            session
+                .location
+                .asn
+                .map(|a| a.to_string())
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
+// replaces "{name}" placeholders, leaving unknown ones untouched
+pub fn render(template: &str, variables: &[(&str, String)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            variables
+                .iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                rendered.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let variables = vec![
+            ("username", "Notch".to_string()),
+            ("ip", "203.0.113.7".to_string()),
+        ];
+        assert_eq!(
+            render("Bye {username} ({ip})", &variables),
+            "Bye Notch (203.0.113.7)"
+        );
+        assert_eq!(
+            render("{unknown} {username}{", &variables),
+            "{unknown} Notch{"
+        );
+        assert_eq!(render("{{username}}", &variables), "{Notch}");
+    }
+
+    #[test]
+    fn test_choose() {
+        assert_eq!(Templates::from("a").choose(), "a");
+        assert_eq!(Templates::Many(vec![]).choose(), "");
+
+        let templates = Templates::Many(vec!["a".to_string(), "b".to_string()]);
+        for _ in 0..10 {
+            assert!(["a", "b"].contains(&templates.choose()));
+        }
+    }
+
+    #[test]
+    fn test_format_uuid() {
+        assert_eq!(
+            format_uuid(0x069a79f444e94726a5befca90e38aaf5),
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+        );
+    }
+}
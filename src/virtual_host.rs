@@ -0,0 +1,361 @@
+use crate::template::{AuthResult, Templates};
+use config::Config;
+use log::warn;
+use serde::Deserialize;
+use std::fs;
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub version_name: String,
+    pub version_protocol: u16,
+    pub version_protocol_min: Option<i32>,
+    pub version_protocol_max: Option<i32>,
+    pub description: String,
+    pub favicon: Option<String>,
+    pub players_max: u16,
+    pub players_online: u16,
+    pub online_mode: bool,
+    pub disconnect_reason: Templates,
+    pub disconnect_reason_authenticated: Option<Templates>,
+    pub disconnect_reason_unauthenticated: Option<Templates>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VirtualHost {
+    hostnames: Vec<String>,
+    version_name: Option<String>,
+    version_protocol: Option<u16>,
+    version_protocol_min: Option<i32>,
+    version_protocol_max: Option<i32>,
+    description: Option<String>,
+    // the path in the configuration, replaced by the loaded data URL
+    favicon: Option<String>,
+    players_max: Option<u16>,
+    players_online: Option<u16>,
+    online_mode: Option<bool>,
+    disconnect_reason: Option<Templates>,
+    disconnect_reason_authenticated: Option<Templates>,
+    disconnect_reason_unauthenticated: Option<Templates>,
+}
+
+// the top-level profile and the virtual hosts overriding it
+#[derive(Debug)]
+pub struct VirtualHosts {
+    pub default: Profile,
+    virtual_hosts: Vec<VirtualHost>,
+}
+
+impl VirtualHosts {
+    pub fn from_config(config: &Config) -> VirtualHosts {
+        let mut virtual_hosts = config
+            .get::<Vec<VirtualHost>>("virtual_hosts")
+            .unwrap_or_default();
+        // the favicons are read once here instead of on every handshake
+        for virtual_host in &mut virtual_hosts {
+            virtual_host.favicon = virtual_host
+                .favicon
+                .take()
+                .and_then(|path| load_favicon(&path));
+        }
+        VirtualHosts {
+            default: Profile::from_config(config),
+            virtual_hosts,
+        }
+    }
+
+    // every virtual host shows the new description until the configuration is reloaded
+    pub fn set_description(&mut self, description: &str) {
+        self.default.description = description.to_string();
+        for virtual_host in &mut self.virtual_hosts {
+            virtual_host.description = None;
+        }
+    }
+
+    pub fn set_players(&mut self, online: u16, max: u16) {
+        self.default.players_online = online;
+        self.default.players_max = max;
+        for virtual_host in &mut self.virtual_hosts {
+            virtual_host.players_online = None;
+            virtual_host.players_max = None;
+        }
+    }
+
+    pub fn resolve(&self, server_address: &str) -> Profile {
+        let profile = self.default.clone();
+        match find_virtual_host(&self.virtual_hosts, &normalize_hostname(server_address)) {
+            Some(virtual_host) => profile.overridden_by(virtual_host),
+            None => profile,
+        }
+    }
+}
+
+impl Profile {
+    const DEFAULT_VERSION_NAME: &'static str = "1.20.2";
+    const DEFAULT_VERSION_PROTOCOL: u16 = 764;
+    const DEFAULT_DESCRIPTION: &'static str = "A Minecraft Server";
+    const DEFAULT_PLAYERS_MAX: u16 = 20;
+    const DEFAULT_PLAYERS_ONLINE: u16 = 0;
+    const DEFAULT_ONLINE_MODE: bool = true;
+    const DEFAULT_DISCONNECT_REASON: &'static str =
+        "You are banned from this server.\nReason: Banned by an operator.";
+
+    pub fn from_config(config: &Config) -> Profile {
+        Profile {
+            version_name: config
+                .get::<String>("version_name")
+                .unwrap_or(Self::DEFAULT_VERSION_NAME.into()),
+            version_protocol: config
+                .get::<u16>("version_protocol")
+                .unwrap_or(Self::DEFAULT_VERSION_PROTOCOL),
+            version_protocol_min: config.get::<i32>("version_protocol_min").ok(),
+            version_protocol_max: config.get::<i32>("version_protocol_max").ok(),
+            description: config
+                .get::<String>("description")
+                .unwrap_or(Self::DEFAULT_DESCRIPTION.into()),
+            favicon: config
+                .get::<String>("favicon")
+                .ok()
+                .and_then(|path| load_favicon(&path)),
+            players_max: config
+                .get::<u16>("players_max")
+                .unwrap_or(Self::DEFAULT_PLAYERS_MAX),
+            players_online: config
+                .get::<u16>("players_online")
+                .unwrap_or(Self::DEFAULT_PLAYERS_ONLINE),
+            online_mode: config
+                .get::<bool>("online_mode")
+                .unwrap_or(Self::DEFAULT_ONLINE_MODE),
+            disconnect_reason: config
+                .get::<Templates>("disconnect_reason")
+                .unwrap_or(Self::DEFAULT_DISCONNECT_REASON.into()),
+            disconnect_reason_authenticated: config
+                .get::<Templates>("disconnect_reason_authenticated")
+                .ok(),
+            disconnect_reason_unauthenticated: config
+                .get::<Templates>("disconnect_reason_unauthenticated")
+                .ok(),
+        }
+    }
+
+    fn overridden_by(self, virtual_host: &VirtualHost) -> Profile {
+        Profile {
+            version_name: virtual_host
+                .version_name
+                .clone()
+                .unwrap_or(self.version_name),
+            version_protocol: virtual_host
+                .version_protocol
+                .unwrap_or(self.version_protocol),
+            version_protocol_min: virtual_host
+                .version_protocol_min
+                .or(self.version_protocol_min),
+            version_protocol_max: virtual_host
+                .version_protocol_max
+                .or(self.version_protocol_max),
+            description: virtual_host.description.clone().unwrap_or(self.description),
+            favicon: virtual_host.favicon.clone().or(self.favicon),
+            players_max: virtual_host.players_max.unwrap_or(self.players_max),
+            players_online: virtual_host.players_online.unwrap_or(self.players_online),
+            online_mode: virtual_host.online_mode.unwrap_or(self.online_mode),
+            disconnect_reason: virtual_host
+                .disconnect_reason
+                .clone()
+                .unwrap_or(self.disconnect_reason),
+            disconnect_reason_authenticated: virtual_host
+                .disconnect_reason_authenticated
+                .clone()
+                .or(self.disconnect_reason_authenticated),
+            disconnect_reason_unauthenticated: virtual_host
+                .disconnect_reason_unauthenticated
+                .clone()
+                .or(self.disconnect_reason_unauthenticated),
+        }
+    }
+
+    // returns the vanilla disconnect message for an unsupported version
+    pub fn check_protocol_version(&self, protocol_version: i32) -> Result<(), String> {
+        if self
+            .version_protocol_min
+            .is_some_and(|min| protocol_version < min)
+        {
+            return Err(format!("Outdated client! Please use {}", self.version_name));
+        }
+        if self
+            .version_protocol_max
+            .is_some_and(|max| protocol_version > max)
+        {
+            return Err(format!(
+                "Outdated server! I'm still on {}",
+                self.version_name
+            ));
+        }
+        Ok(())
+    }
+
+    // the client's own version is echoed back when it is within the supported range
+    pub fn status_protocol_version(&self, protocol_version: Option<i32>) -> i32 {
+        let has_range = self.version_protocol_min.is_some() || self.version_protocol_max.is_some();
+        match protocol_version {
+            Some(v) if has_range && self.check_protocol_version(v).is_ok() => v,
+            _ => self.version_protocol as i32,
+        }
+    }
+
+    pub fn disconnect_templates(&self, auth_result: AuthResult) -> &Templates {
+        let templates = match auth_result {
+            AuthResult::Success => &self.disconnect_reason_authenticated,
+            AuthResult::Failure => &self.disconnect_reason_unauthenticated,
+            AuthResult::Skipped => &None,
+        };
+        templates.as_ref().unwrap_or(&self.disconnect_reason)
+    }
+}
+
+// strip Forge markers (e.g. "\0FML\0") and trailing dots
+pub fn normalize_hostname(server_address: &str) -> String {
+    let hostname = server_address.split('\0').next().unwrap_or_default();
+    hostname.trim_end_matches('.').to_lowercase()
+}
+
+fn find_virtual_host<'a>(
+    virtual_hosts: &'a [VirtualHost],
+    hostname: &str,
+) -> Option<&'a VirtualHost> {
+    virtual_hosts.iter().find(|virtual_host| {
+        virtual_host
+            .hostnames
+            .iter()
+            .any(|h| normalize_hostname(h) == hostname)
+    })
+}
+
+fn load_favicon(path: &str) -> Option<String> {
+    match fs::read(path) {
+        Ok(bytes) => Some(format!(
+            "data:image/png;base64,{}",
+            openssl::base64::encode_block(&bytes)
+        )),
+        Err(e) => {
+            warn!("Could not read favicon {}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn virtual_host(hostnames: &[&str]) -> VirtualHost {
+        VirtualHost {
+            hostnames: hostnames.iter().map(|h| h.to_string()).collect(),
+            version_name: None,
+            version_protocol: None,
+            version_protocol_min: None,
+            version_protocol_max: None,
+            description: None,
+            favicon: None,
+            players_max: None,
+            players_online: None,
+            online_mode: None,
+            disconnect_reason: None,
+            disconnect_reason_authenticated: None,
+            disconnect_reason_unauthenticated: None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_hostname() {
+        assert_eq!(normalize_hostname("mc.example.com"), "mc.example.com");
+        assert_eq!(normalize_hostname("MC.Example.com."), "mc.example.com");
+        assert_eq!(
+            normalize_hostname("mc.example.com\0FML\0"),
+            "mc.example.com"
+        );
+        assert_eq!(
+            normalize_hostname("mc.example.com.\0FML2\0"),
+            "mc.example.com"
+        );
+    }
+
+    #[test]
+    fn test_find_virtual_host() {
+        let virtual_hosts = [
+            virtual_host(&["a.example.com"]),
+            virtual_host(&["b.example.com", "c.example.com."]),
+        ];
+
+        let found = find_virtual_host(&virtual_hosts, "c.example.com").unwrap();
+        assert_eq!(found.hostnames[0], "b.example.com");
+        assert!(find_virtual_host(&virtual_hosts, "d.example.com").is_none());
+    }
+
+    #[test]
+    fn test_protocol_version_range() {
+        let mut profile = Profile::from_config(&Config::default());
+        profile.version_name = "1.19.4-1.20.2".to_string();
+        profile.version_protocol = 764;
+        assert_eq!(profile.check_protocol_version(47), Ok(()));
+        assert_eq!(profile.status_protocol_version(Some(762)), 764);
+
+        profile.version_protocol_min = Some(762);
+        profile.version_protocol_max = Some(764);
+        assert_eq!(profile.check_protocol_version(763), Ok(()));
+        assert_eq!(
+            profile.check_protocol_version(47),
+            Err("Outdated client! Please use 1.19.4-1.20.2".to_string())
+        );
+        assert_eq!(
+            profile.check_protocol_version(765),
+            Err("Outdated server! I'm still on 1.19.4-1.20.2".to_string())
+        );
+        assert_eq!(profile.status_protocol_version(Some(762)), 762);
+        assert_eq!(profile.status_protocol_version(Some(47)), 764);
+        assert_eq!(profile.status_protocol_version(None), 764);
+    }
+
+    #[test]
+    fn test_virtual_host_favicon() {
+        let path = std::env::temp_dir().join("fake-minecraft-server-favicon.png");
+        fs::write(&path, b"png").unwrap();
+        let config = Config::builder()
+            .add_source(config::File::from_str(
+                &format!(
+                    "[[virtual_hosts]]\nhostnames = [\"a.example.com\"]\nfavicon = {:?}\n",
+                    path.to_str().unwrap()
+                ),
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        let virtual_hosts = VirtualHosts::from_config(&config);
+
+        // already loaded, so the file is not needed any more
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            virtual_hosts.resolve("a.example.com").favicon,
+            Some("data:image/png;base64,cG5n".to_string())
+        );
+        assert_eq!(virtual_hosts.resolve("b.example.com").favicon, None);
+    }
+
+    #[test]
+    fn test_set_description_and_players() {
+        let mut overridden = virtual_host(&["a.example.com"]);
+        overridden.description = Some("A".to_string());
+        overridden.players_online = Some(1);
+        let mut virtual_hosts = VirtualHosts {
+            default: Profile::from_config(&Config::default()),
+            virtual_hosts: vec![overridden],
+        };
+        assert_eq!(virtual_hosts.resolve("a.example.com").description, "A");
+
+        virtual_hosts.set_description("Maintenance");
+        virtual_hosts.set_players(5, 10);
+        for server_address in ["a.example.com", "b.example.com"] {
+            let profile = virtual_hosts.resolve(server_address);
+            assert_eq!(profile.description, "Maintenance");
+            assert_eq!((profile.players_online, profile.players_max), (5, 10));
+        }
+    }
+}